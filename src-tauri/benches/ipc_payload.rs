@@ -0,0 +1,75 @@
+//! Compares the two ways a captured frame can be handed to the webview:
+//! the old PNG -> base64 data URL string inside a JSON IPC response, and the
+//! raw PNG bytes returned through `tauri::ipc::Response`.
+//!
+//! Both paths start from the same 5K frame and include the PNG encode, so the
+//! difference between them is the overhead the binary response removes.
+
+use base64::{engine::general_purpose, Engine as _};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use std::io::Cursor;
+use xcap::image::{ImageFormat, Rgba, RgbaImage};
+
+const WIDTH: u32 = 5120;
+const HEIGHT: u32 = 2880;
+
+/// A frame with flat areas and gradients, closer to a desktop screenshot than
+/// random noise, which PNG cannot compress at all
+fn sample_frame() -> RgbaImage {
+    RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        if (x / 640 + y / 360) % 3 == 0 {
+            Rgba([240, 240, 240, 255])
+        } else {
+            Rgba([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8, 255])
+        }
+    })
+}
+
+fn encode_png(frame: &RgbaImage) -> Vec<u8> {
+    let mut buffer = Cursor::new(Vec::new());
+    frame.write_to(&mut buffer, ImageFormat::Png).unwrap();
+    buffer.into_inner()
+}
+
+/// What the commands returned before: a data URL string that the IPC layer
+/// serializes into a JSON response body
+fn data_url_response(png: Vec<u8>) -> Vec<u8> {
+    let data_url = format!(
+        "data:image/png;base64,{}",
+        general_purpose::STANDARD.encode(&png)
+    );
+    serde_json::to_vec(&data_url).unwrap()
+}
+
+/// What the commands return now: the PNG bytes become the response body as is
+fn binary_response(png: Vec<u8>) -> tauri::ipc::Response {
+    tauri::ipc::Response::new(png)
+}
+
+fn ipc_payload(c: &mut Criterion) {
+    let frame = sample_frame();
+    let png = encode_png(&frame);
+
+    let mut group = c.benchmark_group("ipc_payload_5k");
+    group.sample_size(10);
+
+    group.bench_function("encode_and_data_url", |b| {
+        b.iter(|| data_url_response(encode_png(&frame)))
+    });
+    group.bench_function("encode_and_binary_response", |b| {
+        b.iter(|| binary_response(encode_png(&frame)))
+    });
+
+    // The transport step alone, from already encoded PNG bytes
+    group.bench_function("data_url", |b| {
+        b.iter_batched(|| png.clone(), data_url_response, BatchSize::LargeInput)
+    });
+    group.bench_function("binary_response", |b| {
+        b.iter_batched(|| png.clone(), binary_response, BatchSize::LargeInput)
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, ipc_payload);
+criterion_main!(benches);
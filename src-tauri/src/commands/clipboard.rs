@@ -1,22 +1,19 @@
-use base64::{engine::general_purpose, Engine as _};
 use std::io::Cursor;
 use tauri::image::Image;
+use tauri::ipc::{InvokeBody, Request};
 use tauri::AppHandle;
 use tauri_plugin_clipboard_manager::ClipboardExt;
 use xcap::image::{GenericImageView, ImageReader};
 
+/// Copy an encoded image to the clipboard. The image bytes are sent as the raw
+/// IPC body (an `ArrayBuffer` or `Uint8Array` on the frontend).
 #[tauri::command]
-pub async fn copy_image_to_clipboard(app: AppHandle, data_url: String) -> Result<(), String> {
-    let base64_data = data_url
-        .split(',')
-        .nth(1)
-        .ok_or("Invalid data URL format")?;
+pub async fn copy_image_to_clipboard(app: AppHandle, request: Request<'_>) -> Result<(), String> {
+    let InvokeBody::Raw(image_bytes) = request.body() else {
+        return Err("Expected raw image bytes".to_string());
+    };
 
-    let image_bytes = general_purpose::STANDARD
-        .decode(base64_data)
-        .map_err(|e| format!("Failed to decode base64: {}", e))?;
-
-    let img = ImageReader::new(Cursor::new(&image_bytes))
+    let img = ImageReader::new(Cursor::new(image_bytes))
         .with_guessed_format()
         .map_err(|e| format!("Failed to read image format: {}", e))?
         .decode()
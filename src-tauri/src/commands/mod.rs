@@ -1,15 +1,33 @@
 pub mod clipboard;
 pub mod greet;
+pub mod remote_browser;
 pub mod screenshot;
 pub mod settings;
 pub mod sftp;
 pub mod shortcut;
+pub mod upload_history;
+pub mod upload_queue;
 pub mod window;
 
 pub use clipboard::copy_image_to_clipboard;
 pub use greet::greet;
-pub use screenshot::{capture_full_screenshot, capture_screenshot, save_base64_image};
+pub use remote_browser::{
+    delete_remote_path, list_remote_dir, rename_remote_path, stat_remote_path,
+};
+pub use screenshot::{
+    capture_full_screenshot, capture_screenshot, capture_virtual_desktop, capture_window,
+    crop_frozen_frame, discard_frozen_frames, get_frozen_frame, list_monitors, list_windows,
+    save_base64_image, show_region_selector,
+};
 pub use settings::{get_settings, update_settings};
-pub use sftp::{test_sftp_connection, upload_to_sftp};
+pub use sftp::{
+    answer_sftp_auth_prompt, answer_sftp_host_key, cancel_upload, test_sftp_connection,
+    upload_to_sftp,
+};
 pub use shortcut::{register_escape_shortcut, register_shortcut, unregister_escape_shortcut};
+pub use upload_history::{copy_upload_link, delete_uploaded_file, list_upload_history};
+pub use upload_queue::{
+    drop_queued_upload, list_upload_queue, move_queued_upload, queue_upload, retry_queued_upload,
+    start_upload_queue_worker,
+};
 pub use window::{hide_main_window, show_main_window};
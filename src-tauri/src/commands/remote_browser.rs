@@ -0,0 +1,39 @@
+use crate::commands::sftp::saved_uploader;
+use crate::services::{RemoteEntry, Settings, SftpError, SftpUploader};
+use tauri::AppHandle;
+
+/// Run `op` on a blocking thread with an uploader for the saved connection
+async fn with_saved_uploader<T: Send + 'static>(
+    app: &AppHandle,
+    op: impl FnOnce(&SftpUploader) -> Result<T, SftpError> + Send + 'static,
+) -> Result<T, String> {
+    let uploader = saved_uploader(app, &Settings::load()?).map_err(|e| e.to_string())?;
+
+    tauri::async_runtime::spawn_blocking(move || op(&uploader))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// List a directory on the server. An empty path lists the login directory.
+#[tauri::command]
+pub async fn list_remote_dir(app: AppHandle, path: String) -> Result<Vec<RemoteEntry>, String> {
+    with_saved_uploader(&app, move |uploader| uploader.list_remote_dir(&path)).await
+}
+
+#[tauri::command]
+pub async fn stat_remote_path(app: AppHandle, path: String) -> Result<RemoteEntry, String> {
+    with_saved_uploader(&app, move |uploader| uploader.stat_remote(&path)).await
+}
+
+/// Rename or move a remote file or directory without replacing anything
+#[tauri::command]
+pub async fn rename_remote_path(app: AppHandle, from: String, to: String) -> Result<(), String> {
+    with_saved_uploader(&app, move |uploader| uploader.rename_remote(&from, &to)).await
+}
+
+/// Delete a remote file or empty directory
+#[tauri::command]
+pub async fn delete_remote_path(app: AppHandle, path: String) -> Result<(), String> {
+    with_saved_uploader(&app, move |uploader| uploader.delete_remote(&path)).await
+}
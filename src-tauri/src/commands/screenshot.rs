@@ -1,86 +1,309 @@
+use crate::services::capture::{self, CapturedFrame};
+use crate::services::encoding;
+use crate::services::{
+    CaptureRegion, CaptureSize, CoordinateSpace, FrameCache, MonitorInfo, OutputFormat, Settings,
+    WindowInfo,
+};
 use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
 use std::io::Cursor;
 use std::path::PathBuf;
-use xcap::image::GenericImageView;
-use xcap::Monitor;
+use std::time::Duration;
+use tauri::ipc::Response;
+use tauri::{AppHandle, Emitter, LogicalPosition, Manager, PhysicalPosition, Position, State};
+use xcap::image::{DynamicImage, RgbaImage};
+
+#[derive(Debug, Serialize)]
+pub struct SavedScreenshot {
+    pub path: String,
+    #[serde(flatten)]
+    pub size: CaptureSize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SavedImage {
+    pub path: String,
+    /// Bytes saved by the PNG optimization pass
+    pub bytes_saved: u64,
+}
+
+/// A monitor frame captured when the region selector was opened
+#[derive(Debug, Clone, Serialize)]
+pub struct FrozenFrame {
+    pub id: u32,
+    pub monitor: MonitorInfo,
+}
+
+/// Payload of the `show-region-selector` event
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionSelectorPayload {
+    pub frames: Vec<FrozenFrame>,
+    /// Frame of the monitor under the cursor when the capture was triggered
+    pub active_frame_id: Option<u32>,
+}
+
+/// Grab every monitor into the frame cache, then ask the frontend to show the
+/// region selector on top of the frozen frames.
+///
+/// If capturing fails the event is still emitted with no frames, so the
+/// frontend can fall back to `capture_full_screenshot`.
+pub fn show_region_selector(app: &AppHandle) {
+    let app = app.clone();
+
+    std::thread::spawn(move || {
+        // Keep our own window out of the frozen frame
+        if let Some(window) = app.get_webview_window("main") {
+            if window.is_visible().unwrap_or(false) {
+                let _ = window.hide();
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+
+        let cache = app.state::<FrameCache>();
+
+        let payload = match capture::capture_all_monitors() {
+            Ok(captures) => {
+                let active_monitor = capture::resolve_monitor(None, cursor_point(&app))
+                    .ok()
+                    .map(|m| m.id());
+                let (monitors, frames): (Vec<_>, Vec<_>) = captures.into_iter().unzip();
+                let ids = cache.replace(frames);
+
+                let frames: Vec<FrozenFrame> = ids
+                    .into_iter()
+                    .zip(monitors)
+                    .map(|(id, monitor)| FrozenFrame { id, monitor })
+                    .collect();
+                let active_frame = frames
+                    .iter()
+                    .find(|f| Some(f.monitor.id) == active_monitor)
+                    .or(frames.first());
+
+                // The frontend goes fullscreen on whatever monitor holds the
+                // window, so put it on the one whose frame it will show
+                if let Some(frame) = active_frame {
+                    move_to_monitor(&app, &frame.monitor);
+                }
+                let active_frame_id = active_frame.map(|f| f.id);
+
+                RegionSelectorPayload {
+                    frames,
+                    active_frame_id,
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to capture frozen frames: {}", e);
+                cache.clear();
+                RegionSelectorPayload {
+                    frames: Vec::new(),
+                    active_frame_id: None,
+                }
+            }
+        };
+
+        let _ = app.emit("show-region-selector", payload);
+    });
+}
+
+/// Move the main window to the top-left corner of `monitor`. xcap reports
+/// monitor bounds in physical pixels on Windows and logical points elsewhere.
+fn move_to_monitor(app: &AppHandle, monitor: &MonitorInfo) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+
+    let position: Position = if cfg!(target_os = "windows") {
+        PhysicalPosition::new(monitor.x, monitor.y).into()
+    } else {
+        LogicalPosition::new(monitor.x, monitor.y).into()
+    };
+    if let Err(e) = window.set_position(position) {
+        eprintln!("Failed to move window to monitor {}: {}", monitor.name, e);
+    }
+}
+
+/// Cursor position in physical pixels, which `resolve_monitor` converts to
+/// each monitor's desktop units
+fn cursor_point(app: &AppHandle) -> Option<(i32, i32)> {
+    app.cursor_position()
+        .ok()
+        .map(|position| (position.x.round() as i32, position.y.round() as i32))
+}
 
 #[tauri::command]
-pub fn capture_full_screenshot() -> Result<String, String> {
-    let monitors = Monitor::all().map_err(|e| format!("Failed to get monitors: {}", e))?;
+pub fn list_monitors() -> Result<Vec<MonitorInfo>, String> {
+    capture::list_monitors().map_err(|e| e.to_string())
+}
 
-    if monitors.is_empty() {
-        return Err("No monitors found".to_string());
+/// Capture either the stitched virtual desktop or a single monitor
+fn capture_source(
+    app: &AppHandle,
+    monitor_id: Option<u32>,
+    all_monitors: bool,
+) -> Result<CapturedFrame, String> {
+    if all_monitors {
+        return capture::capture_virtual_desktop().map_err(|e| e.to_string());
     }
 
-    let primary_monitor = &monitors[0];
-    let screenshot = primary_monitor
-        .capture_image()
-        .map_err(|e| format!("Failed to capture screenshot: {}", e))?;
+    let monitor =
+        capture::resolve_monitor(monitor_id, cursor_point(app)).map_err(|e| e.to_string())?;
+    capture::capture_monitor(&monitor).map_err(|e| e.to_string())
+}
 
+/// Encode a frame as PNG and hand the bytes to the webview as a binary IPC
+/// response, which arrives as an `ArrayBuffer` without any base64 round trip
+fn png_response(screenshot: &RgbaImage) -> Result<Response, String> {
     let mut buffer = Cursor::new(Vec::new());
     screenshot
         .write_to(&mut buffer, xcap::image::ImageFormat::Png)
         .map_err(|e| format!("Failed to encode screenshot: {}", e))?;
 
-    let base64_image = general_purpose::STANDARD.encode(buffer.into_inner());
-    Ok(format!("data:image/png;base64,{}", base64_image))
+    Ok(Response::new(buffer.into_inner()))
 }
 
 #[tauri::command]
-pub fn capture_screenshot(
-    x: i32,
-    y: i32,
-    width: u32,
-    height: u32,
-    save_path: String,
-) -> Result<String, String> {
-    let monitors = Monitor::all().map_err(|e| format!("Failed to get monitors: {}", e))?;
+pub fn capture_full_screenshot(
+    app: AppHandle,
+    monitor_id: Option<u32>,
+) -> Result<Response, String> {
+    let frame = capture_source(&app, monitor_id, false)?;
+    png_response(&frame.image)
+}
 
-    if monitors.is_empty() {
-        return Err("No monitors found".to_string());
-    }
+#[tauri::command]
+pub fn capture_virtual_desktop() -> Result<Response, String> {
+    let frame = capture::capture_virtual_desktop().map_err(|e| e.to_string())?;
+    png_response(&frame.image)
+}
 
-    let primary_monitor = &monitors[0];
-    let screenshot = primary_monitor
-        .capture_image()
-        .map_err(|e| format!("Failed to capture screenshot: {}", e))?;
+#[tauri::command]
+pub fn get_frozen_frame(frames: State<'_, FrameCache>, frame_id: u32) -> Result<Response, String> {
+    let frame = frames.get(frame_id).map_err(|e| e.to_string())?;
+    png_response(&frame.image)
+}
 
-    let cropped = if width > 0 && height > 0 {
-        screenshot
-            .view(x as u32, y as u32, width, height)
-            .to_image()
-    } else {
-        screenshot
-    };
+#[tauri::command]
+pub fn crop_frozen_frame(
+    frames: State<'_, FrameCache>,
+    frame_id: u32,
+    region: CaptureRegion,
+    coordinate_space: CoordinateSpace,
+) -> Result<Response, String> {
+    let frame = frames.get(frame_id).map_err(|e| e.to_string())?;
+    let cropped = frame
+        .crop(region, coordinate_space)
+        .map_err(|e| e.to_string())?;
+    png_response(&cropped)
+}
 
-    let path = PathBuf::from(&save_path);
+#[tauri::command]
+pub fn discard_frozen_frames(frames: State<'_, FrameCache>) {
+    frames.clear();
+}
 
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create directory '{}': {}", parent.display(), e))?;
-    }
+#[tauri::command]
+pub fn list_windows() -> Result<Vec<WindowInfo>, String> {
+    capture::list_windows().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn capture_window(
+    window_id: u32,
+    margin: Option<u32>,
+    shadow: Option<bool>,
+) -> Result<Response, String> {
+    let frame = capture::capture_window(window_id, margin.unwrap_or(0), shadow.unwrap_or(false))
+        .map_err(|e| e.to_string())?;
+    png_response(&frame.image)
+}
+
+/// Capture, encode and save a screenshot. Encoding (AVIF, PNG optimization)
+/// can take seconds on large frames, so it runs on a blocking thread.
+#[tauri::command]
+pub async fn capture_screenshot(
+    app: AppHandle,
+    region: Option<CaptureRegion>,
+    coordinate_space: CoordinateSpace,
+    save_path: String,
+    monitor_id: Option<u32>,
+    all_monitors: Option<bool>,
+) -> Result<SavedScreenshot, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        // Region coordinates are relative to the top-left corner of the chosen
+        // monitor, or of the bounding box around all monitors when stitching
+        let frame = capture_source(&app, monitor_id, all_monitors.unwrap_or(false))?;
+        let scale_factor = frame.scale_factor;
+
+        let cropped = match region {
+            Some(region) => frame
+                .crop(region, coordinate_space)
+                .map_err(|e| e.to_string())?,
+            None => frame.image,
+        };
 
-    cropped
-        .save(&path)
-        .map_err(|e| format!("Failed to save screenshot: {}", e))?;
+        let size = CaptureSize::from_physical(cropped.width(), cropped.height(), scale_factor);
+        let output = Settings::load()?.output;
+        let encoded = encoding::encode_image(&DynamicImage::ImageRgba8(cropped), &output)
+            .map_err(|e| e.to_string())?;
+        let optimized = encoding::optimize_png(encoded, output.png_optimization);
 
-    Ok(save_path)
+        let path = write_image_file(&save_path, &optimized.data, output.format)?;
+
+        Ok(SavedScreenshot { path, size })
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
+/// Save an image sent from the editor, re-encoding it into the configured
+/// output format when it differs and optimizing PNG output if enabled. The
+/// returned path is the one actually written, whose extension matches the
+/// output format.
+///
+/// Runs on a blocking thread so re-encoding doesn't freeze the UI.
 #[tauri::command]
-pub fn save_base64_image(base64_data: String, save_path: String) -> Result<String, String> {
-    let image_data = general_purpose::STANDARD
-        .decode(&base64_data)
-        .map_err(|e| format!("Failed to decode base64: {}", e))?;
+pub async fn save_base64_image(
+    base64_data: String,
+    save_path: String,
+) -> Result<SavedImage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let image_data = general_purpose::STANDARD
+            .decode(&base64_data)
+            .map_err(|e| format!("Failed to decode base64: {}", e))?;
+
+        let output = Settings::load()?.output;
+
+        let encoded = if encoding::detect_format(&image_data) == Some(output.format) {
+            image_data
+        } else {
+            let image = encoding::decode_image(&image_data).map_err(|e| e.to_string())?;
+            encoding::encode_image(&image, &output).map_err(|e| e.to_string())?
+        };
+        let optimized = encoding::optimize_png(encoded, output.png_optimization);
+
+        let path = write_image_file(&save_path, &optimized.data, output.format)?;
+
+        Ok(SavedImage {
+            path,
+            bytes_saved: optimized.bytes_saved,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-    let path = PathBuf::from(&save_path);
+/// Write encoded image bytes to `save_path`, replacing its extension with the
+/// one matching `format`
+fn write_image_file(save_path: &str, bytes: &[u8], format: OutputFormat) -> Result<String, String> {
+    let mut path = PathBuf::from(save_path);
+    path.set_extension(format.extension());
 
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)
             .map_err(|e| format!("Failed to create directory '{}': {}", parent.display(), e))?;
     }
 
-    std::fs::write(&path, image_data).map_err(|e| format!("Failed to save image: {}", e))?;
+    std::fs::write(&path, bytes).map_err(|e| format!("Failed to save image: {}", e))?;
 
-    Ok(save_path)
+    Ok(path.to_string_lossy().to_string())
 }
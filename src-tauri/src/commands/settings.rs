@@ -1,4 +1,5 @@
-use crate::services::Settings;
+use crate::services::{SessionManager, Settings};
+use tauri::State;
 
 #[tauri::command]
 pub fn get_settings() -> Result<Settings, String> {
@@ -6,7 +7,11 @@ pub fn get_settings() -> Result<Settings, String> {
 }
 
 #[tauri::command]
-pub fn update_settings(settings: Settings, password: Option<String>) -> Result<(), String> {
+pub fn update_settings(
+    sessions: State<'_, SessionManager>,
+    settings: Settings,
+    password: Option<String>,
+) -> Result<(), String> {
     let mut settings_to_save = settings;
 
     if let Some(new_password) = password {
@@ -16,5 +21,10 @@ pub fn update_settings(settings: Settings, password: Option<String>) -> Result<(
         }
     }
 
-    settings_to_save.save()
+    settings_to_save.save()?;
+
+    // Sessions were opened with the old connection settings
+    sessions.clear();
+
+    Ok(())
 }
@@ -1,37 +1,309 @@
-use crate::services::SftpUploader;
+use crate::commands::upload_history::record_upload;
+use crate::services::encoding::UploadLimits;
+use crate::services::{
+    ActiveUploads, AuthChallenge, AuthPrompter, AuthPrompts, RemoteModes, SessionManager, Settings,
+    SftpAuth, SftpAuthMethod, SftpError, SftpUploader, UnknownHostKey, UploadProgress, UploadQueue,
+    UploadResult, UploadTarget,
+};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
 
+/// How long to wait for the user to answer a keyboard-interactive or host key prompt
+const AUTH_PROMPT_TIMEOUT: Duration = Duration::from_secs(120);
+/// How often a pending prompt checks whether its upload was cancelled
+const AUTH_PROMPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Payload of the `sftp-auth-prompt` event
+#[derive(Debug, Clone, Serialize)]
+struct AuthPromptPayload {
+    request_id: u32,
+    #[serde(flatten)]
+    challenge: AuthChallenge,
+}
+
+/// Appended to the error of an upload that was added to the upload queue
+const UPLOAD_QUEUED: &str = "Queued to retry once the server is reachable.";
+
+const TRAY_ID: &str = "main-tray";
+
+/// Emit `upload-progress` and mirror it in the tray tooltip
+fn report_progress(app: &AppHandle, progress: &UploadProgress) {
+    let _ = app.emit("upload-progress", progress.clone());
+
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let percent = progress.bytes_sent * 100 / progress.total_bytes.max(1);
+        let _ = tray.set_tooltip(Some(format!("Uploading... {}%", percent)));
+    }
+}
+
+/// Payload of the `sftp-host-key-prompt` event
+#[derive(Debug, Clone, Serialize)]
+struct HostKeyPromptPayload {
+    request_id: u32,
+    #[serde(flatten)]
+    key: UnknownHostKey,
+}
+
+/// Forwards keyboard-interactive challenges and unknown host keys to the
+/// frontend and waits for `answer_sftp_auth_prompt` or `answer_sftp_host_key`.
+/// Stops waiting early once `cancel` is set.
+struct EventAuthPrompter {
+    app: AppHandle,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl EventAuthPrompter {
+    /// Emit `event` with a payload built from a fresh request id and wait for
+    /// the answer
+    fn ask<P: Serialize + Clone>(
+        &self,
+        event: &str,
+        payload: impl FnOnce(u32) -> P,
+    ) -> Option<Vec<String>> {
+        let prompts = self.app.state::<AuthPrompts>();
+        let (request_id, receiver) = prompts.register();
+
+        if let Err(e) = self.app.emit(event, payload(request_id)) {
+            eprintln!("Failed to emit {}: {}", event, e);
+            prompts.remove(request_id);
+            return None;
+        }
+
+        let deadline = Instant::now() + AUTH_PROMPT_TIMEOUT;
+        let response = loop {
+            let cancelled = self
+                .cancel
+                .as_ref()
+                .is_some_and(|flag| flag.load(Ordering::SeqCst));
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if cancelled || remaining.is_zero() {
+                break None;
+            }
+
+            match receiver.recv_timeout(remaining.min(AUTH_PROMPT_POLL_INTERVAL)) {
+                Ok(response) => break response,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break None,
+            }
+        };
+        prompts.remove(request_id);
+        response
+    }
+}
+
+impl AuthPrompter for EventAuthPrompter {
+    fn prompt(&self, challenge: &AuthChallenge) -> Option<Vec<String>> {
+        self.ask("sftp-auth-prompt", |request_id| AuthPromptPayload {
+            request_id,
+            challenge: challenge.clone(),
+        })
+    }
+
+    fn confirm_host_key(&self, key: &UnknownHostKey) -> bool {
+        self.ask("sftp-host-key-prompt", |request_id| HostKeyPromptPayload {
+            request_id,
+            key: key.clone(),
+        })
+        .is_some()
+    }
+}
+
+fn event_prompter(app: AppHandle, cancel: Option<Arc<AtomicBool>>) -> Arc<dyn AuthPrompter> {
+    Arc::new(EventAuthPrompter { app, cancel })
+}
+
+/// Answer a pending `sftp-auth-prompt`. Pass `None` to cancel it.
+#[tauri::command]
+pub fn answer_sftp_auth_prompt(
+    prompts: State<'_, AuthPrompts>,
+    request_id: u32,
+    responses: Option<Vec<String>>,
+) -> Result<(), String> {
+    if prompts.answer(request_id, responses) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Authentication prompt {} is no longer pending",
+            request_id
+        ))
+    }
+}
+
+/// Stop a running upload. The upload itself fails with a cancelled error and
+/// removes whatever it already wrote to the server.
+#[tauri::command]
+pub fn cancel_upload(uploads: State<'_, ActiveUploads>, upload_id: String) -> Result<(), String> {
+    if uploads.cancel(&upload_id) {
+        Ok(())
+    } else {
+        Err(format!("No running upload with id '{}'", upload_id))
+    }
+}
+
+/// Answer a pending `sftp-host-key-prompt`
+#[tauri::command]
+pub fn answer_sftp_host_key(
+    prompts: State<'_, AuthPrompts>,
+    request_id: u32,
+    trusted: bool,
+) -> Result<(), String> {
+    // A trusted key is answered like a completed prompt with no responses
+    if prompts.answer(request_id, trusted.then(Vec::new)) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Host key prompt {} is no longer pending",
+            request_id
+        ))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConnectionTestResult {
+    pub message: String,
+    /// Settings that will likely cause problems even though connecting worked
+    pub warnings: Vec<String>,
+}
+
+/// Test the connection with the values currently in the settings form.
+/// `auth_methods`, `key_passphrase` and the modes fall back to the saved
+/// settings.
+///
+/// Runs on a blocking thread so keyboard-interactive prompts can be answered
+/// while it waits.
 #[tauri::command]
-pub fn test_sftp_connection(
+#[allow(clippy::too_many_arguments)]
+pub async fn test_sftp_connection(
+    app: AppHandle,
     host: String,
     port: u16,
     username: String,
     password: String,
-) -> Result<String, String> {
-    let password_opt = if password.is_empty() {
-        None
-    } else {
-        Some(password)
+    auth_methods: Option<Vec<SftpAuthMethod>>,
+    key_passphrase: Option<String>,
+    file_mode: Option<u32>,
+    dir_mode: Option<u32>,
+) -> Result<ConnectionTestResult, String> {
+    let saved = Settings::load()?.sftp;
+    let auth_methods = auth_methods.unwrap_or(saved.auth_methods);
+    let key_passphrase = key_passphrase.unwrap_or(saved.key_passphrase);
+    let modes = RemoteModes {
+        file_mode: file_mode.or(saved.file_mode),
+        dir_mode: dir_mode.or(saved.dir_mode),
     };
 
-    let uploader = SftpUploader::new(
-        host.clone(),
-        port,
-        username.clone(),
-        password_opt,
-        String::new(),
+    let auth = SftpAuth::from_methods(&auth_methods, &password, &key_passphrase)
+        .map_err(|e| e.to_string())?;
+    let uploader = SftpUploader::new(host.clone(), port, username.clone(), auth, String::new())
+        .map_err(|e| e.to_string())?
+        .with_retry(saved.retry)
+        .with_auth_prompter(event_prompter(app, None));
+
+    tauri::async_runtime::spawn_blocking(move || uploader.test_connection())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    Ok(ConnectionTestResult {
+        message: format!("Successfully connected to {}@{}:{}", username, host, port),
+        warnings: modes.warnings(),
+    })
+}
+
+/// Build an uploader for the given connection with everything else taken
+/// from `settings`
+pub(crate) fn configured_uploader(
+    app: &AppHandle,
+    settings: &Settings,
+    host: String,
+    port: u16,
+    username: String,
+    password: &str,
+    remote_path: String,
+) -> Result<SftpUploader, SftpError> {
+    let sftp = &settings.sftp;
+    let auth = SftpAuth::from_methods(&sftp.auth_methods, password, &sftp.key_passphrase)?;
+
+    Ok(SftpUploader::new(host, port, username, auth, remote_path)?
+        .with_profile(&sftp.profile_name)
+        .with_modes(RemoteModes {
+            file_mode: sftp.file_mode,
+            dir_mode: sftp.dir_mode,
+        })
+        .with_output_config(settings.output.clone())
+        .with_upload_limits(UploadLimits {
+            max_dimension: sftp.max_dimension,
+            max_file_size: sftp.max_file_size,
+        })
+        .with_naming(sftp.naming, sftp.collision_policy)
+        .with_retry(sftp.retry)
+        .with_session_manager(
+            app.state::<SessionManager>().inner().clone(),
+            Duration::from_secs(sftp.session_idle_timeout_secs),
+        )
+        .with_auth_prompter(event_prompter(app.clone(), None)))
+}
+
+/// Build an uploader for the connection saved in `settings`, for work that
+/// happens without the settings form, e.g. queued uploads
+pub(crate) fn saved_uploader(
+    app: &AppHandle,
+    settings: &Settings,
+) -> Result<SftpUploader, SftpError> {
+    let sftp = &settings.sftp;
+    configured_uploader(
+        app,
+        settings,
+        sftp.host.clone(),
+        sftp.port,
+        sftp.username.clone(),
+        &sftp.password,
+        sftp.remote_path.clone(),
     )
-    .map_err(|e| e.to_string())?;
+}
 
-    uploader.test_connection().map_err(|e| e.to_string())?;
+/// Make `upload_id` cancellable, including while it waits for an auth
+/// prompt, and report its progress. Call `finish_tracking` once the upload is
+/// over.
+pub(crate) fn track_upload(
+    app: &AppHandle,
+    uploader: SftpUploader,
+    upload_id: &str,
+) -> SftpUploader {
+    let cancel_flag = app.state::<ActiveUploads>().register(upload_id);
+    let progress_app = app.clone();
 
-    Ok(format!(
-        "Successfully connected to {}@{}:{}",
-        username, host, port
-    ))
+    uploader
+        .with_auth_prompter(event_prompter(app.clone(), Some(cancel_flag.clone())))
+        .with_cancel_flag(cancel_flag)
+        .with_progress(upload_id.to_string(), move |progress| {
+            report_progress(&progress_app, progress)
+        })
 }
 
+pub(crate) fn finish_tracking(app: &AppHandle, upload_id: &str) {
+    app.state::<ActiveUploads>().finish(upload_id);
+
+    // Don't leave a stale percentage behind if the upload failed part way
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let _ = tray.set_tooltip(None::<&str>);
+    }
+}
+
+/// Upload a file, emitting `upload-progress` events tagged with `upload_id`.
+/// An id is generated when the frontend doesn't pass one.
+///
+/// Uploads that fail because the server can't be reached are added to the
+/// upload queue and retried in the background.
 #[tauri::command]
-pub fn upload_to_sftp(
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_to_sftp(
+    app: AppHandle,
+    upload_id: Option<String>,
     file_path: String,
     filename: String,
     host: String,
@@ -39,7 +311,7 @@ pub fn upload_to_sftp(
     username: String,
     password: String,
     remote_path: String,
-) -> Result<String, String> {
+) -> Result<UploadResult, String> {
     println!(
         "Upload SFTP - host: '{}', username: '{}', password length: {}",
         host,
@@ -57,19 +329,63 @@ pub fn upload_to_sftp(
             "SFTP username is not configured. Please configure SFTP settings first.".to_string(),
         );
     }
-    if password.trim().is_empty() {
-        return Err(
-            "SFTP password is not configured. Please configure SFTP settings first.".to_string(),
-        );
-    }
 
-    let password_opt = Some(password);
-    let uploader = SftpUploader::new(host, port, username, password_opt, remote_path)
-        .map_err(|e| e.to_string())?;
+    let settings = Settings::load()?;
+    let target = UploadTarget {
+        host: host.clone(),
+        port,
+        username: username.clone(),
+        remote_path: remote_path.clone(),
+    };
+    let uploader = configured_uploader(
+        &app,
+        &settings,
+        host,
+        port,
+        username,
+        &password,
+        remote_path,
+    )
+    .map_err(|e| e.to_string())?;
 
-    let remote_file = uploader
-        .upload_file(&file_path, &filename)
-        .map_err(|e| e.to_string())?;
+    // Queued uploads outlive the app, so generated ids must be unique across runs
+    let upload_id = upload_id.unwrap_or_else(|| format!("upload-{}", uuid::Uuid::new_v4()));
+    let uploader = track_upload(&app, uploader, &upload_id);
+
+    let (local_path, remote_filename) = (file_path.clone(), filename.clone());
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        uploader.upload_file(&local_path, &remote_filename)
+    })
+    .await;
+    finish_tracking(&app, &upload_id);
+
+    let result = match result.map_err(|e| e.to_string())? {
+        Ok(result) => result,
+        Err(e) if e.is_transient() => {
+            app.state::<UploadQueue>()
+                .push(
+                    &upload_id,
+                    &file_path,
+                    &filename,
+                    target,
+                    Some(e.to_string()),
+                )
+                .map_err(|queue_error| format!("{}. {}", e, queue_error))?;
+            return Err(format!("{}. {}", e, UPLOAD_QUEUED));
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+
+    if result.bytes_saved > 0 {
+        println!("PNG optimization saved {} bytes", result.bytes_saved);
+    }
+    record_upload(
+        &app,
+        &settings,
+        (&target.host, target.port, &target.username),
+        &file_path,
+        &result,
+    );
 
-    Ok(remote_file)
+    Ok(result)
 }
@@ -1,3 +1,4 @@
+use crate::commands::show_region_selector;
 use tauri::{AppHandle, Emitter};
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
 
@@ -14,7 +15,7 @@ pub fn register_shortcut(app: AppHandle, shortcut_str: String) -> Result<(), Str
     app.global_shortcut()
         .on_shortcut(shortcut, move |app, _shortcut, event| {
             if event.state == ShortcutState::Pressed {
-                let _ = app.emit("show-region-selector", ());
+                show_region_selector(app);
             }
         })
         .map_err(|e| format!("Failed to register shortcut: {}", e))?;
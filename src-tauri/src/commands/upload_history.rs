@@ -0,0 +1,114 @@
+use crate::commands::sftp::saved_uploader;
+use crate::services::upload_history::NewUploadRecord;
+use crate::services::{Settings, UploadHistory, UploadHistoryPage, UploadResult};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+/// Public link of an upload, the base URL joined with its `url_path`
+pub(crate) fn public_url(settings: &Settings, url_path: &str) -> String {
+    format!(
+        "{}/{}",
+        settings.sftp.base_url.trim_end_matches('/'),
+        url_path
+    )
+}
+
+/// Add a finished upload to the history. A failure here is only logged, the
+/// upload itself worked.
+pub(crate) fn record_upload(
+    app: &AppHandle,
+    settings: &Settings,
+    server: (&str, u16, &str),
+    local_path: &str,
+    result: &UploadResult,
+) {
+    let (host, port, username) = server;
+    let url = public_url(settings, &result.url_path);
+    let recorded = app.state::<UploadHistory>().record(NewUploadRecord {
+        local_path,
+        remote_path: &result.remote_path,
+        url: &url,
+        profile: &settings.sftp.profile_name,
+        host,
+        port,
+        username,
+        size: result.size,
+        sha256: &result.sha256,
+    });
+
+    if let Err(e) = recorded {
+        eprintln!("Failed to record upload of '{}': {}", local_path, e);
+    }
+}
+
+/// One page of the upload history, newest first, optionally filtered by
+/// `search`
+#[tauri::command]
+pub fn list_upload_history(
+    history: State<'_, UploadHistory>,
+    search: Option<String>,
+    page: u32,
+    page_size: u32,
+) -> Result<UploadHistoryPage, String> {
+    history
+        .list(search.as_deref(), page, page_size)
+        .map_err(|e| e.to_string())
+}
+
+/// Copy the public link of a past upload to the clipboard, returning it
+#[tauri::command]
+pub fn copy_upload_link(
+    app: AppHandle,
+    history: State<'_, UploadHistory>,
+    id: i64,
+) -> Result<String, String> {
+    let record = history.get(id).map_err(|e| e.to_string())?;
+
+    app.clipboard()
+        .write_text(record.url.clone())
+        .map_err(|e| format!("Failed to copy link to clipboard: {}", e))?;
+
+    Ok(record.url)
+}
+
+/// Delete a past upload from the server and remove it from the history.
+///
+/// Only done when the saved connection still points at the server the file
+/// was uploaded to, so the same path is never removed from another server.
+#[tauri::command]
+pub async fn delete_uploaded_file(app: AppHandle, id: i64) -> Result<(), String> {
+    let record = app
+        .state::<UploadHistory>()
+        .get(id)
+        .map_err(|e| e.to_string())?;
+
+    let settings = Settings::load()?;
+    let sftp = &settings.sftp;
+    if (sftp.host.as_str(), sftp.port, sftp.username.as_str())
+        != (record.host.as_str(), record.port, record.username.as_str())
+    {
+        return Err(format!(
+            "'{}' was uploaded to {}@{}:{}, but the saved connection is {}@{}:{}",
+            record.remote_path,
+            record.username,
+            record.host,
+            record.port,
+            sftp.username,
+            sftp.host,
+            sftp.port
+        ));
+    }
+
+    let uploader = saved_uploader(&app, &settings).map_err(|e| e.to_string())?;
+
+    let remote_path = record.remote_path.clone();
+    tauri::async_runtime::spawn_blocking(move || uploader.delete_remote_file(&remote_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    println!("Deleted '{}' from the server", record.remote_path);
+    app.state::<UploadHistory>()
+        .remove(id)
+        .map_err(|e| e.to_string())
+}
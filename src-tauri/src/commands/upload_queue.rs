@@ -0,0 +1,208 @@
+use crate::commands::sftp::{finish_tracking, saved_uploader, track_upload};
+use crate::commands::upload_history::{public_url, record_upload};
+use crate::services::{
+    ActiveUploads, QueuedUpload, QueuedUploadStatus, Settings, SftpError, UploadQueue,
+    UploadResult, UploadTarget,
+};
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+/// How often the worker checks whether queued uploads can go through now
+const QUEUE_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Payload of the `queued-upload-finished` event
+#[derive(Debug, Clone, Serialize)]
+struct QueuedUploadFinished {
+    id: String,
+    filename: String,
+    remote_path: String,
+    url: String,
+    copied_to_clipboard: bool,
+}
+
+/// Tell the frontend the queue changed, e.g. to refresh a list of it
+fn emit_queue_changed(app: &AppHandle) {
+    let _ = app.emit("upload-queue-changed", app.state::<UploadQueue>().list());
+}
+
+/// Upload one queued item with the connection from the saved settings
+fn upload_queued(
+    app: &AppHandle,
+    settings: &Settings,
+    item: &QueuedUpload,
+) -> Result<UploadResult, SftpError> {
+    let uploader = track_upload(app, saved_uploader(app, settings)?, &item.id);
+    let result = uploader.upload_file(&item.file_path, &item.filename);
+    finish_tracking(app, &item.id);
+    result
+}
+
+/// Copy the link of a finished upload if enabled and let the frontend show it
+fn announce_upload(
+    app: &AppHandle,
+    settings: &Settings,
+    item: &QueuedUpload,
+    result: UploadResult,
+) {
+    let url = public_url(settings, &result.url_path);
+
+    let copied_to_clipboard = settings.sftp.copy_to_clipboard
+        && match app.clipboard().write_text(url.clone()) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Failed to copy link to clipboard: {}", e);
+                false
+            }
+        };
+
+    println!("Queued upload '{}' finished: {}", item.filename, url);
+    let _ = app.emit(
+        "queued-upload-finished",
+        QueuedUploadFinished {
+            id: item.id.clone(),
+            filename: item.filename.clone(),
+            remote_path: result.remote_path,
+            url,
+            copied_to_clipboard,
+        },
+    );
+}
+
+/// Upload pending items in order until the queue is empty or the server
+/// turns out to be unreachable
+fn process_upload_queue(app: &AppHandle) {
+    let queue = app.state::<UploadQueue>();
+
+    loop {
+        let item = match queue.start_next() {
+            Ok(Some(item)) => item,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+        emit_queue_changed(app);
+
+        let settings = match Settings::load() {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("Failed to load settings for queued upload: {}", e);
+                let _ = queue.record_failure(&item.id, e, QueuedUploadStatus::Pending);
+                return;
+            }
+        };
+
+        // Never send an upload to a server or directory it wasn't meant for
+        if item.target != UploadTarget::from(&settings.sftp) {
+            let target = &item.target;
+            eprintln!(
+                "Queued upload '{}' was meant for {}@{}:{}{}, not sending it",
+                item.filename, target.username, target.host, target.port, target.remote_path
+            );
+            let _ = queue.record_failure(
+                &item.id,
+                format!(
+                    "The SFTP connection settings changed since this upload was queued for {}@{}:{}",
+                    target.username, target.host, target.port
+                ),
+                QueuedUploadStatus::Failed,
+            );
+            emit_queue_changed(app);
+            continue;
+        }
+
+        match upload_queued(app, &settings, &item) {
+            Ok(result) => {
+                // The item may have been dropped while it was uploading
+                let _ = queue.remove(&item.id);
+                let target = &item.target;
+                let server = (target.host.as_str(), target.port, target.username.as_str());
+                record_upload(app, &settings, server, &item.file_path, &result);
+                announce_upload(app, &settings, &item, result);
+            }
+            Err(e) => {
+                let transient = e.is_transient();
+                let status = if transient {
+                    QueuedUploadStatus::Pending
+                } else {
+                    QueuedUploadStatus::Failed
+                };
+
+                eprintln!("Queued upload '{}' failed: {}", item.filename, e);
+                let _ = queue.record_failure(&item.id, e.to_string(), status);
+
+                // Still offline, so the rest of the queue would fail the same way
+                if transient {
+                    emit_queue_changed(app);
+                    return;
+                }
+            }
+        }
+        emit_queue_changed(app);
+    }
+}
+
+/// Start the background thread that works through the upload queue. It
+/// runs right away for anything left over from the last session, then
+/// whenever the queue changes and every `QUEUE_RETRY_INTERVAL`.
+pub fn start_upload_queue_worker(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        process_upload_queue(&app);
+        app.state::<UploadQueue>().wait(QUEUE_RETRY_INTERVAL);
+    });
+}
+
+#[tauri::command]
+pub fn list_upload_queue(queue: State<'_, UploadQueue>) -> Vec<QueuedUpload> {
+    queue.list()
+}
+
+/// Defer an upload to the saved server, e.g. when the user knows they're
+/// offline
+#[tauri::command]
+pub fn queue_upload(
+    queue: State<'_, UploadQueue>,
+    file_path: String,
+    filename: String,
+) -> Result<QueuedUpload, String> {
+    let target = UploadTarget::from(&Settings::load()?.sftp);
+    let id = format!("queued-{}", uuid::Uuid::new_v4());
+    queue
+        .push(&id, &file_path, &filename, target, None)
+        .map_err(|e| e.to_string())
+}
+
+/// Try a queued upload again now, including one that failed permanently
+#[tauri::command]
+pub fn retry_queued_upload(
+    queue: State<'_, UploadQueue>,
+    id: String,
+) -> Result<QueuedUpload, String> {
+    queue.retry(&id).map_err(|e| e.to_string())
+}
+
+/// Move a queued upload to `position`, where 0 is tried first
+#[tauri::command]
+pub fn move_queued_upload(
+    queue: State<'_, UploadQueue>,
+    id: String,
+    position: usize,
+) -> Result<(), String> {
+    queue.move_to(&id, position).map_err(|e| e.to_string())
+}
+
+/// Remove an upload from the queue, stopping it if it's running. The local
+/// file is kept.
+#[tauri::command]
+pub fn drop_queued_upload(
+    queue: State<'_, UploadQueue>,
+    uploads: State<'_, ActiveUploads>,
+    id: String,
+) -> Result<(), String> {
+    queue.remove(&id).map_err(|e| e.to_string())?;
+    uploads.cancel(&id);
+    Ok(())
+}
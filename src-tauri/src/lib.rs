@@ -3,13 +3,21 @@ mod services;
 mod setup;
 
 use commands::{
-    capture_full_screenshot, capture_screenshot, copy_image_to_clipboard, get_settings,
-    hide_main_window, register_escape_shortcut, register_shortcut, save_base64_image,
-    show_main_window, test_sftp_connection, unregister_escape_shortcut, update_settings,
-    upload_to_sftp, greet,
+    answer_sftp_auth_prompt, answer_sftp_host_key, cancel_upload, capture_full_screenshot,
+    capture_screenshot, capture_virtual_desktop, capture_window, copy_image_to_clipboard,
+    copy_upload_link, crop_frozen_frame, delete_remote_path, delete_uploaded_file,
+    discard_frozen_frames, drop_queued_upload, get_frozen_frame, get_settings, greet,
+    hide_main_window, list_monitors, list_remote_dir, list_upload_history, list_upload_queue,
+    list_windows, move_queued_upload, queue_upload, register_escape_shortcut, register_shortcut,
+    rename_remote_path, retry_queued_upload, save_base64_image, show_main_window,
+    start_upload_queue_worker, stat_remote_path, test_sftp_connection, unregister_escape_shortcut,
+    update_settings, upload_to_sftp,
+};
+use services::{
+    ActiveUploads, AuthPrompts, FrameCache, SessionManager, UploadHistory, UploadQueue,
 };
 use setup::{setup_global_shortcuts, setup_tray};
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -19,31 +27,63 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_clipboard_manager::init())
+        .manage(FrameCache::default())
+        .manage(AuthPrompts::default())
+        .manage(SessionManager::default())
+        .manage(ActiveUploads::default())
         .setup(|app| {
             setup_tray(app.handle())?;
             setup_global_shortcuts(app.handle())?;
+            // Uploads still work without a history, the history commands report
+            // why it's missing
+            app.manage(UploadHistory::open().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                UploadHistory::unavailable(&e)
+            }));
+            app.manage(UploadQueue::load());
+            start_upload_queue_worker(app.handle().clone());
             Ok(())
         })
-        .on_window_event(|window, event| {
-            match event {
-                tauri::WindowEvent::CloseRequested { api, .. } => {
-                    let _ = window.emit("window-close-requested", ());
-                    window.hide().unwrap();
-                    api.prevent_close();
-                }
-                tauri::WindowEvent::Focused(false) => {}
-                _ => {}
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::CloseRequested { api, .. } => {
+                let _ = window.emit("window-close-requested", ());
+                window.hide().unwrap();
+                api.prevent_close();
             }
+            tauri::WindowEvent::Focused(false) => {}
+            _ => {}
         })
         .invoke_handler(tauri::generate_handler![
             greet,
             capture_screenshot,
             capture_full_screenshot,
+            capture_virtual_desktop,
+            list_monitors,
+            list_windows,
+            capture_window,
+            get_frozen_frame,
+            crop_frozen_frame,
+            discard_frozen_frames,
             save_base64_image,
             get_settings,
             update_settings,
             test_sftp_connection,
             upload_to_sftp,
+            cancel_upload,
+            answer_sftp_auth_prompt,
+            answer_sftp_host_key,
+            list_upload_queue,
+            queue_upload,
+            retry_queued_upload,
+            move_queued_upload,
+            drop_queued_upload,
+            list_upload_history,
+            copy_upload_link,
+            delete_uploaded_file,
+            list_remote_dir,
+            stat_remote_path,
+            rename_remote_path,
+            delete_remote_path,
             show_main_window,
             hide_main_window,
             register_shortcut,
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Cancellation flags of the uploads currently running, by upload id
+#[derive(Default)]
+pub struct ActiveUploads {
+    inner: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl ActiveUploads {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Track a new upload, returning the flag its transfer loop should watch
+    pub fn register(&self, upload_id: &str) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.lock().insert(upload_id.to_string(), flag.clone());
+        flag
+    }
+
+    /// Ask a running upload to stop. Returns false if no upload has that id.
+    pub fn cancel(&self, upload_id: &str) -> bool {
+        match self.lock().get(upload_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop tracking an upload once it has finished, failed or been cancelled
+    pub fn finish(&self, upload_id: &str) {
+        self.lock().remove(upload_id);
+    }
+}
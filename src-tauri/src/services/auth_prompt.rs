@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Responses to a prompt, or `None` if the user dismissed it
+pub type PromptResponse = Option<Vec<String>>;
+
+/// Keyboard-interactive prompts waiting for the user to answer them in the
+/// frontend. The SFTP thread registers a prompt, emits an event carrying its
+/// id and blocks on the receiver until `answer` is called with that id.
+#[derive(Default)]
+pub struct AuthPrompts {
+    inner: Mutex<AuthPromptsInner>,
+}
+
+#[derive(Default)]
+struct AuthPromptsInner {
+    next_id: u32,
+    pending: HashMap<u32, Sender<PromptResponse>>,
+}
+
+impl AuthPrompts {
+    fn lock(&self) -> MutexGuard<'_, AuthPromptsInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Register a new pending prompt, returning its id and the receiver the
+    /// answer will arrive on
+    pub fn register(&self) -> (u32, Receiver<PromptResponse>) {
+        let (sender, receiver) = mpsc::channel();
+        let mut inner = self.lock();
+        inner.next_id = inner.next_id.wrapping_add(1);
+        let id = inner.next_id;
+        inner.pending.insert(id, sender);
+        (id, receiver)
+    }
+
+    /// Deliver the user's answer. Returns false if the prompt is no longer
+    /// pending, e.g. because it timed out.
+    pub fn answer(&self, id: u32, responses: PromptResponse) -> bool {
+        match self.lock().pending.remove(&id) {
+            Some(sender) => sender.send(responses).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Stop waiting for a prompt
+    pub fn remove(&self, id: u32) {
+        self.lock().pending.remove(&id);
+    }
+}
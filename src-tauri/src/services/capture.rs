@@ -0,0 +1,865 @@
+use serde::{Deserialize, Serialize};
+use xcap::image::{imageops, Rgba, RgbaImage};
+use xcap::{Monitor, Window};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+    #[error("Failed to get monitors: {0}")]
+    MonitorQueryFailed(String),
+    #[error("No monitors found")]
+    NoMonitors,
+    #[error("Monitor with id {0} not found")]
+    MonitorNotFound(u32),
+    #[error("Failed to get windows: {0}")]
+    WindowQueryFailed(String),
+    #[error("Window with id {0} not found")]
+    WindowNotFound(u32),
+    #[error("Window with id {0} is minimized")]
+    WindowMinimized(u32),
+    #[error("Failed to capture screenshot: {0}")]
+    CaptureFailed(String),
+    #[error("Captured frame {0} is no longer available")]
+    FrameNotFound(u32),
+    #[error("Capture region is empty")]
+    EmptyRegion,
+    #[error(
+        "Capture region {width}x{height} at ({x}, {y}) is outside the {bounds_width}x{bounds_height} capture"
+    )]
+    RegionOutOfBounds {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        bounds_width: u32,
+        bounds_height: u32,
+    },
+}
+
+/// Alpha of the darkest part of the drop shadow drawn behind captured windows
+const SHADOW_ALPHA: u8 = 110;
+
+/// Largest padding added around a captured window, in pixels
+const MAX_WINDOW_MARGIN: u32 = 256;
+
+/// Coordinate space of a region sent from the frontend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoordinateSpace {
+    /// Device-independent pixels, as used by the web overlay
+    #[default]
+    Logical,
+    /// Raw pixels of the captured image
+    Physical,
+}
+
+impl CoordinateSpace {
+    /// Convert a value in this space to physical pixels
+    pub fn to_physical(self, value: f64, scale_factor: f32) -> f64 {
+        match self {
+            CoordinateSpace::Logical => value * scale_factor as f64,
+            CoordinateSpace::Physical => value,
+        }
+    }
+}
+
+/// A rectangular area of a captured frame, as sent by the region selector.
+///
+/// The raw values are untrusted: the origin may be negative and the area may
+/// run past the edge of the frame. Use [`CaptureRegion::crop`] to get pixels
+/// out of it safely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct CaptureRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CaptureRegion {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Convert the region from `space` to physical pixels. Edges are rounded
+    /// individually so adjacent regions stay adjacent after scaling.
+    pub fn to_physical(self, space: CoordinateSpace, scale_factor: f32) -> Self {
+        let left = space.to_physical(self.x as f64, scale_factor).round();
+        let top = space.to_physical(self.y as f64, scale_factor).round();
+        let right = space
+            .to_physical(self.x as f64 + self.width as f64, scale_factor)
+            .round();
+        let bottom = space
+            .to_physical(self.y as f64 + self.height as f64, scale_factor)
+            .round();
+
+        Self {
+            x: left as i32,
+            y: top as i32,
+            width: (right - left).max(0.0) as u32,
+            height: (bottom - top).max(0.0) as u32,
+        }
+    }
+
+    /// Overlapping part of two regions, or `None` when they do not overlap
+    pub fn intersect(&self, other: &CaptureRegion) -> Option<CaptureRegion> {
+        let left = (self.x as i64).max(other.x as i64);
+        let top = (self.y as i64).max(other.y as i64);
+        let right = (self.x as i64 + self.width as i64).min(other.x as i64 + other.width as i64);
+        let bottom = (self.y as i64 + self.height as i64).min(other.y as i64 + other.height as i64);
+
+        if left >= right || top >= bottom {
+            return None;
+        }
+
+        Some(Self {
+            x: left as i32,
+            y: top as i32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        })
+    }
+
+    /// Intersect the region with a `bounds_width` x `bounds_height` frame.
+    ///
+    /// Returns `(x, y, width, height)` guaranteed to lie inside the frame,
+    /// `EmptyRegion` for a zero-sized region and `RegionOutOfBounds` when the
+    /// region does not overlap the frame at all.
+    pub fn clamp_to(
+        &self,
+        bounds_width: u32,
+        bounds_height: u32,
+    ) -> Result<(u32, u32, u32, u32), CaptureError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(CaptureError::EmptyRegion);
+        }
+
+        let left = (self.x as i64).max(0);
+        let top = (self.y as i64).max(0);
+        let right = (self.x as i64 + self.width as i64).min(bounds_width as i64);
+        let bottom = (self.y as i64 + self.height as i64).min(bounds_height as i64);
+
+        if left >= right || top >= bottom {
+            return Err(CaptureError::RegionOutOfBounds {
+                x: self.x,
+                y: self.y,
+                width: self.width,
+                height: self.height,
+                bounds_width,
+                bounds_height,
+            });
+        }
+
+        Ok((
+            left as u32,
+            top as u32,
+            (right - left) as u32,
+            (bottom - top) as u32,
+        ))
+    }
+
+    /// Crop the part of `image` covered by this region
+    pub fn crop(&self, image: &RgbaImage) -> Result<RgbaImage, CaptureError> {
+        let (x, y, width, height) = self.clamp_to(image.width(), image.height())?;
+        Ok(imageops::crop_imm(image, x, y, width, height).to_image())
+    }
+}
+
+/// Where one monitor ended up inside a stitched virtual-desktop frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorPlacement {
+    /// Area covered by the monitor in logical pixels, relative to the
+    /// top-left corner of the logical desktop bounds
+    pub logical: CaptureRegion,
+    /// Top-left corner of the monitor inside the stitched image
+    pub physical_x: i64,
+    pub physical_y: i64,
+    pub scale_factor: f32,
+}
+
+/// A captured image together with the scale factor of the display it came from
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    pub image: RgbaImage,
+    pub scale_factor: f32,
+    /// Layout of the monitors in a stitched frame, empty for single captures
+    pub monitors: Vec<MonitorPlacement>,
+}
+
+impl CapturedFrame {
+    fn new(image: RgbaImage, scale_factor: f32) -> Self {
+        Self {
+            image,
+            scale_factor: sanitize_scale_factor(scale_factor),
+            monitors: Vec::new(),
+        }
+    }
+
+    /// Convert a region in `space` to physical pixels of this frame.
+    ///
+    /// Logical regions on a stitched frame are converted monitor by monitor,
+    /// so a region dragged across displays with different scale factors
+    /// covers the same area on each of them.
+    pub fn region_to_physical(
+        &self,
+        region: CaptureRegion,
+        space: CoordinateSpace,
+    ) -> CaptureRegion {
+        if space == CoordinateSpace::Physical || self.monitors.is_empty() {
+            return region.to_physical(space, self.scale_factor);
+        }
+
+        let mut bounds: Option<(i64, i64, i64, i64)> = None;
+
+        for monitor in &self.monitors {
+            let Some(part) = region.intersect(&monitor.logical) else {
+                continue;
+            };
+
+            let local = CaptureRegion::new(
+                part.x - monitor.logical.x,
+                part.y - monitor.logical.y,
+                part.width,
+                part.height,
+            )
+            .to_physical(space, monitor.scale_factor);
+
+            let left = monitor.physical_x + local.x as i64;
+            let top = monitor.physical_y + local.y as i64;
+            let right = left + local.width as i64;
+            let bottom = top + local.height as i64;
+
+            bounds = Some(match bounds {
+                Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(right), b.max(bottom)),
+                None => (left, top, right, bottom),
+            });
+        }
+
+        match bounds {
+            Some((left, top, right, bottom)) => CaptureRegion::new(
+                left.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                top.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                (right - left).clamp(0, u32::MAX as i64) as u32,
+                (bottom - top).clamp(0, u32::MAX as i64) as u32,
+            ),
+            // Entirely in a gap between monitors, nothing there to capture
+            None => region.to_physical(space, self.scale_factor),
+        }
+    }
+
+    /// Crop the part of the frame covered by `region`
+    pub fn crop(
+        &self,
+        region: CaptureRegion,
+        space: CoordinateSpace,
+    ) -> Result<RgbaImage, CaptureError> {
+        self.region_to_physical(region, space).crop(&self.image)
+    }
+}
+
+fn sanitize_scale_factor(scale_factor: f32) -> f32 {
+    if scale_factor.is_finite() && scale_factor > 0.0 {
+        scale_factor
+    } else {
+        1.0
+    }
+}
+
+/// Size of a captured image in both logical and physical pixels
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CaptureSize {
+    pub logical_width: u32,
+    pub logical_height: u32,
+    pub physical_width: u32,
+    pub physical_height: u32,
+    pub scale_factor: f32,
+}
+
+impl CaptureSize {
+    pub fn from_physical(width: u32, height: u32, scale_factor: f32) -> Self {
+        Self {
+            logical_width: (width as f32 / scale_factor).round() as u32,
+            logical_height: (height as f32 / scale_factor).round() as u32,
+            physical_width: width,
+            physical_height: height,
+            scale_factor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorInfo {
+    pub id: u32,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub is_primary: bool,
+}
+
+impl From<&Monitor> for MonitorInfo {
+    fn from(monitor: &Monitor) -> Self {
+        Self {
+            id: monitor.id(),
+            name: monitor.name().to_string(),
+            x: monitor.x(),
+            y: monitor.y(),
+            width: monitor.width(),
+            height: monitor.height(),
+            scale_factor: monitor.scale_factor(),
+            is_primary: monitor.is_primary(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowInfo {
+    pub id: u32,
+    pub title: String,
+    pub app_name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub is_minimized: bool,
+}
+
+impl From<&Window> for WindowInfo {
+    fn from(window: &Window) -> Self {
+        Self {
+            id: window.id(),
+            title: window.title().to_string(),
+            app_name: window.app_name().to_string(),
+            x: window.x(),
+            y: window.y(),
+            width: window.width(),
+            height: window.height(),
+            is_minimized: window.is_minimized(),
+        }
+    }
+}
+
+fn all_monitors() -> Result<Vec<Monitor>, CaptureError> {
+    let monitors = Monitor::all().map_err(|e| CaptureError::MonitorQueryFailed(e.to_string()))?;
+
+    if monitors.is_empty() {
+        return Err(CaptureError::NoMonitors);
+    }
+
+    Ok(monitors)
+}
+
+/// Physical pixels per unit of the desktop coordinates xcap reports for a
+/// monitor. xcap uses physical pixels on Windows and logical points on macOS
+/// and X11.
+fn desktop_pixel_ratio(monitor: &Monitor) -> f64 {
+    if cfg!(target_os = "windows") {
+        1.0
+    } else {
+        sanitize_scale_factor(monitor.scale_factor()) as f64
+    }
+}
+
+/// Whether the physical point `(x, y)` lies in the desktop rectangle
+/// `(left, top, width, height)`, with `pixel_ratio` physical pixels per unit
+fn rect_contains_physical(
+    (left, top, width, height): (i32, i32, u32, u32),
+    pixel_ratio: f64,
+    x: i32,
+    y: i32,
+) -> bool {
+    let (x, y) = (x as f64 / pixel_ratio, y as f64 / pixel_ratio);
+    let (left, top) = (left as f64, top as f64);
+
+    x >= left && x < left + width as f64 && y >= top && y < top + height as f64
+}
+
+/// Whether `monitor` contains the point `(x, y)` in physical pixels, as
+/// reported for the cursor
+fn monitor_contains(monitor: &Monitor, x: i32, y: i32) -> bool {
+    rect_contains_physical(
+        (monitor.x(), monitor.y(), monitor.width(), monitor.height()),
+        desktop_pixel_ratio(monitor),
+        x,
+        y,
+    )
+}
+
+/// List all connected monitors with their desktop position and scale factor
+pub fn list_monitors() -> Result<Vec<MonitorInfo>, CaptureError> {
+    Ok(all_monitors()?.iter().map(MonitorInfo::from).collect())
+}
+
+/// Pick the monitor to capture: the requested id if given, otherwise the
+/// monitor under `cursor` (in physical pixels), falling back to the primary
+/// monitor
+pub fn resolve_monitor(
+    monitor_id: Option<u32>,
+    cursor: Option<(i32, i32)>,
+) -> Result<Monitor, CaptureError> {
+    let monitors = all_monitors()?;
+
+    if let Some(id) = monitor_id {
+        return monitors
+            .into_iter()
+            .find(|m| m.id() == id)
+            .ok_or(CaptureError::MonitorNotFound(id));
+    }
+
+    if let Some((x, y)) = cursor {
+        if let Some(monitor) = monitors.iter().find(|m| monitor_contains(m, x, y)) {
+            return Ok(monitor.clone());
+        }
+    }
+
+    let primary = monitors.iter().position(|m| m.is_primary()).unwrap_or(0);
+
+    Ok(monitors[primary].clone())
+}
+
+/// Capture the full contents of a single monitor
+pub fn capture_monitor(monitor: &Monitor) -> Result<CapturedFrame, CaptureError> {
+    let image = monitor
+        .capture_image()
+        .map_err(|e| CaptureError::CaptureFailed(e.to_string()))?;
+
+    Ok(CapturedFrame::new(image, monitor.scale_factor()))
+}
+
+/// Capture every monitor as a separate frame, paired with its description
+pub fn capture_all_monitors() -> Result<Vec<(MonitorInfo, CapturedFrame)>, CaptureError> {
+    all_monitors()?
+        .iter()
+        .map(|monitor| Ok((MonitorInfo::from(monitor), capture_monitor(monitor)?)))
+        .collect()
+}
+
+/// A monitor to place in a stitched frame
+#[derive(Debug, Clone, Copy)]
+struct LayoutInput {
+    /// Desktop rectangle in the units xcap reports for the monitor
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    /// Width of the monitor's captured image
+    image_width: u32,
+    scale_factor: f32,
+}
+
+/// Where a monitor's capture goes in a stitched frame, and the size it has
+/// to be resampled to first
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct MonitorLayout {
+    placement: MonitorPlacement,
+    width: u32,
+    height: u32,
+}
+
+/// Lay out monitors on one canvas, returning its size and where each monitor
+/// goes.
+///
+/// Every monitor is placed with the same factor: its desktop position times
+/// the highest pixel density among the monitors, measured as captured pixels
+/// per desktop unit. That density is the scale factor where xcap reports
+/// logical points (macOS, scaled X11) and 1 where it reports physical pixels
+/// (Windows). Lower-density captures are scaled up to match, so monitors
+/// never overlap or leave gaps that aren't on the desktop too.
+fn layout_monitors(inputs: &[LayoutInput]) -> (u32, u32, Vec<MonitorLayout>) {
+    let density = |input: &LayoutInput| input.image_width as f64 / input.width.max(1) as f64;
+    let canvas_density = inputs.iter().map(density).fold(1.0, f64::max);
+
+    let left = inputs.iter().map(|i| i.x as i64).min().unwrap_or(0);
+    let top = inputs.iter().map(|i| i.y as i64).min().unwrap_or(0);
+    let to_canvas =
+        |value: i64, origin: i64| ((value - origin) as f64 * canvas_density).round() as i64;
+
+    // Logical pixels per desktop unit differ between monitors on Windows,
+    // where xcap reports physical pixels
+    let logical_origin = |input: &LayoutInput| {
+        let logical_per_unit = density(input) / input.scale_factor as f64;
+        (
+            input.x as f64 * logical_per_unit,
+            input.y as f64 * logical_per_unit,
+        )
+    };
+    let logical_left = inputs
+        .iter()
+        .map(|i| logical_origin(i).0)
+        .fold(f64::INFINITY, f64::min);
+    let logical_top = inputs
+        .iter()
+        .map(|i| logical_origin(i).1)
+        .fold(f64::INFINITY, f64::min);
+
+    let layouts: Vec<MonitorLayout> = inputs
+        .iter()
+        .map(|input| {
+            let x = to_canvas(input.x as i64, left);
+            let y = to_canvas(input.y as i64, top);
+            let right = to_canvas(input.x as i64 + input.width as i64, left);
+            let bottom = to_canvas(input.y as i64 + input.height as i64, top);
+
+            let logical_per_unit = density(input) / input.scale_factor as f64;
+            let (logical_x, logical_y) = logical_origin(input);
+
+            MonitorLayout {
+                placement: MonitorPlacement {
+                    logical: CaptureRegion::new(
+                        (logical_x - logical_left).round() as i32,
+                        (logical_y - logical_top).round() as i32,
+                        (input.width as f64 * logical_per_unit).round() as u32,
+                        (input.height as f64 * logical_per_unit).round() as u32,
+                    ),
+                    physical_x: x,
+                    physical_y: y,
+                    scale_factor: (canvas_density / logical_per_unit) as f32,
+                },
+                width: (right - x) as u32,
+                height: (bottom - y) as u32,
+            }
+        })
+        .collect();
+
+    let width = layouts
+        .iter()
+        .map(|l| l.placement.physical_x + l.width as i64)
+        .max()
+        .unwrap_or(0);
+    let height = layouts
+        .iter()
+        .map(|l| l.placement.physical_y + l.height as i64)
+        .max()
+        .unwrap_or(0);
+
+    (width as u32, height as u32, layouts)
+}
+
+/// Capture every monitor and stitch them into one image laid out at their
+/// desktop positions, see [`layout_monitors`]. Gaps between monitors are left
+/// transparent.
+///
+/// The top-left corner of the returned image is the top-left corner of the
+/// bounding box around all monitors. The primary monitor's scale factor is
+/// reported for the whole frame, while [`CapturedFrame::monitors`] keeps the
+/// per-monitor layout used to convert logical regions.
+pub fn capture_virtual_desktop() -> Result<CapturedFrame, CaptureError> {
+    let monitors = all_monitors()?;
+    let frames = monitors
+        .iter()
+        .map(capture_monitor)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let inputs: Vec<LayoutInput> = monitors
+        .iter()
+        .zip(&frames)
+        .map(|(monitor, frame)| LayoutInput {
+            x: monitor.x(),
+            y: monitor.y(),
+            width: monitor.width(),
+            height: monitor.height(),
+            image_width: frame.image.width(),
+            scale_factor: frame.scale_factor,
+        })
+        .collect();
+    let (width, height, layouts) = layout_monitors(&inputs);
+
+    let mut desktop = RgbaImage::new(width, height);
+    for (frame, layout) in frames.iter().zip(&layouts) {
+        let resized;
+        let image = if frame.image.dimensions() == (layout.width, layout.height) {
+            &frame.image
+        } else {
+            resized = imageops::resize(
+                &frame.image,
+                layout.width,
+                layout.height,
+                imageops::FilterType::CatmullRom,
+            );
+            &resized
+        };
+
+        imageops::overlay(
+            &mut desktop,
+            image,
+            layout.placement.physical_x,
+            layout.placement.physical_y,
+        );
+    }
+
+    let primary = monitors.iter().position(|m| m.is_primary()).unwrap_or(0);
+    let mut frame = CapturedFrame::new(desktop, layouts[primary].placement.scale_factor);
+    frame.monitors = layouts.into_iter().map(|layout| layout.placement).collect();
+    Ok(frame)
+}
+
+fn all_windows() -> Result<Vec<Window>, CaptureError> {
+    Window::all().map_err(|e| CaptureError::WindowQueryFailed(e.to_string()))
+}
+
+/// List top-level windows that can be captured, skipping zero-sized ones
+pub fn list_windows() -> Result<Vec<WindowInfo>, CaptureError> {
+    Ok(all_windows()?
+        .iter()
+        .filter(|w| w.width() > 0 && w.height() > 0)
+        .map(WindowInfo::from)
+        .collect())
+}
+
+/// Capture a single window by id.
+///
+/// `margin` adds transparent padding around the window on every side. When
+/// `shadow` is set, a soft drop shadow is drawn into that padding, so it only
+/// shows up with a non-zero margin. The margin is capped at
+/// `MAX_WINDOW_MARGIN` so an oversized value cannot blow up the canvas.
+pub fn capture_window(
+    window_id: u32,
+    margin: u32,
+    shadow: bool,
+) -> Result<CapturedFrame, CaptureError> {
+    let window = all_windows()?
+        .into_iter()
+        .find(|w| w.id() == window_id)
+        .ok_or(CaptureError::WindowNotFound(window_id))?;
+
+    if window.is_minimized() {
+        return Err(CaptureError::WindowMinimized(window_id));
+    }
+
+    let image = window
+        .capture_image()
+        .map_err(|e| CaptureError::CaptureFailed(e.to_string()))?;
+
+    Ok(CapturedFrame::new(
+        decorate_window_image(image, margin.min(MAX_WINDOW_MARGIN), shadow),
+        window.current_monitor().scale_factor(),
+    ))
+}
+
+fn decorate_window_image(image: RgbaImage, margin: u32, shadow: bool) -> RgbaImage {
+    if margin == 0 {
+        return image;
+    }
+
+    let (width, height) = image.dimensions();
+    let mut canvas = RgbaImage::new(width + margin * 2, height + margin * 2);
+
+    if shadow {
+        // Offset the shadow slightly downwards so the window appears lifted
+        let offset = margin / 4;
+        for y in (margin + offset)..(margin + offset + height).min(canvas.height()) {
+            for x in margin..(margin + width) {
+                canvas.put_pixel(x, y, Rgba([0, 0, 0, SHADOW_ALPHA]));
+            }
+        }
+        canvas = imageops::fast_blur(&canvas, margin as f32 / 3.0);
+    }
+
+    imageops::overlay(&mut canvas, &image, margin as i64, margin as i64);
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn physical_cursor_is_matched_against_logical_monitor_bounds() {
+        // A 2x monitor 1440 points wide spans 2880 physical pixels
+        let laptop = (0, 0, 1440, 900);
+        assert!(rect_contains_physical(laptop, 2.0, 2000, 1000));
+        assert!(!rect_contains_physical(laptop, 2.0, 2880, 100));
+
+        let external = (1440, 0, 1920, 1080);
+        assert!(rect_contains_physical(external, 1.0, 2000, 100));
+    }
+
+    #[test]
+    fn clamp_trims_negative_origin() {
+        let region = CaptureRegion::new(-10, -20, 50, 60);
+        assert_eq!(region.clamp_to(100, 100).unwrap(), (0, 0, 40, 40));
+    }
+
+    #[test]
+    fn clamp_trims_region_past_right_and_bottom_edges() {
+        let region = CaptureRegion::new(80, 90, 50, 50);
+        assert_eq!(region.clamp_to(100, 100).unwrap(), (80, 90, 20, 10));
+    }
+
+    #[test]
+    fn clamp_rejects_zero_sized_region() {
+        assert!(matches!(
+            CaptureRegion::new(10, 10, 0, 20).clamp_to(100, 100),
+            Err(CaptureError::EmptyRegion)
+        ));
+        assert!(matches!(
+            CaptureRegion::new(10, 10, 20, 0).clamp_to(100, 100),
+            Err(CaptureError::EmptyRegion)
+        ));
+    }
+
+    #[test]
+    fn clamp_rejects_region_outside_frame() {
+        for region in [
+            CaptureRegion::new(100, 0, 10, 10),
+            CaptureRegion::new(0, 150, 10, 10),
+            CaptureRegion::new(-20, -20, 20, 20),
+        ] {
+            assert!(matches!(
+                region.clamp_to(100, 100),
+                Err(CaptureError::RegionOutOfBounds { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn crop_returns_clamped_pixels() {
+        let image = RgbaImage::new(100, 50);
+        let cropped = CaptureRegion::new(-5, 40, 20, 20).crop(&image).unwrap();
+        assert_eq!(cropped.dimensions(), (15, 10));
+    }
+
+    #[test]
+    fn to_physical_scales_logical_regions() {
+        let region = CaptureRegion::new(10, 20, 100, 50);
+
+        assert_eq!(
+            region.to_physical(CoordinateSpace::Logical, 2.0),
+            CaptureRegion::new(20, 40, 200, 100)
+        );
+        assert_eq!(
+            region.to_physical(CoordinateSpace::Logical, 1.5),
+            CaptureRegion::new(15, 30, 150, 75)
+        );
+        assert_eq!(region.to_physical(CoordinateSpace::Physical, 2.0), region);
+    }
+
+    #[test]
+    fn to_physical_keeps_adjacent_regions_adjacent_at_fractional_scale() {
+        let left = CaptureRegion::new(0, 0, 3, 3).to_physical(CoordinateSpace::Logical, 1.5);
+        let right = CaptureRegion::new(3, 0, 3, 3).to_physical(CoordinateSpace::Logical, 1.5);
+
+        assert_eq!(left.x + left.width as i32, right.x);
+    }
+
+    fn layout_input(x: i32, width: u32, height: u32, scale_factor: f32) -> LayoutInput {
+        LayoutInput {
+            x,
+            y: 0,
+            width,
+            height,
+            image_width: (width as f32 * scale_factor) as u32,
+            scale_factor,
+        }
+    }
+
+    #[test]
+    fn layout_places_mixed_dpi_monitors_side_by_side() {
+        // macOS style: a 2x laptop with a 1x screen to its right, in points
+        let (width, height, layouts) = layout_monitors(&[
+            layout_input(0, 1440, 900, 2.0),
+            layout_input(1440, 1920, 1080, 1.0),
+        ]);
+
+        assert_eq!((width, height), (2880 + 3840, 2160));
+        assert_eq!(
+            (layouts[0].placement.physical_x, layouts[0].width),
+            (0, 2880)
+        );
+        assert_eq!(
+            (layouts[1].placement.physical_x, layouts[1].width),
+            (2880, 3840)
+        );
+        assert_eq!(layouts[1].height, 2160);
+        assert_eq!(
+            layouts[1].placement.logical,
+            CaptureRegion::new(1440, 0, 1920, 1080)
+        );
+        assert_eq!(layouts[0].placement.scale_factor, 2.0);
+        assert_eq!(layouts[1].placement.scale_factor, 2.0);
+    }
+
+    #[test]
+    fn layout_places_low_dpi_monitor_first_without_gap() {
+        let (width, _, layouts) = layout_monitors(&[
+            layout_input(0, 1920, 1080, 1.0),
+            layout_input(1920, 1440, 900, 2.0),
+        ]);
+
+        assert_eq!(layouts[0].width, 3840);
+        assert_eq!(layouts[1].placement.physical_x, 3840);
+        assert_eq!(width, 3840 + 2880);
+    }
+
+    #[test]
+    fn layout_keeps_physical_desktop_coordinates_as_is() {
+        // Windows style: positions and sizes are already physical pixels
+        let physical = |x: i32, width: u32, height: u32, scale_factor: f32| LayoutInput {
+            image_width: width,
+            ..layout_input(x, width, height, scale_factor)
+        };
+        let (width, height, layouts) = layout_monitors(&[
+            physical(-1920, 1920, 1080, 1.0),
+            physical(0, 2880, 1620, 1.5),
+        ]);
+
+        assert_eq!((width, height), (1920 + 2880, 1620));
+        assert_eq!(layouts[1].placement.physical_x, 1920);
+        assert_eq!((layouts[1].width, layouts[1].height), (2880, 1620));
+        assert_eq!(layouts[1].placement.scale_factor, 1.5);
+    }
+
+    #[test]
+    fn region_across_laid_out_monitors_stays_contiguous() {
+        let (width, height, layouts) = layout_monitors(&[
+            layout_input(0, 1440, 900, 2.0),
+            layout_input(1440, 1920, 1080, 1.0),
+        ]);
+        let frame = CapturedFrame {
+            image: RgbaImage::new(width, height),
+            scale_factor: 2.0,
+            monitors: layouts.into_iter().map(|layout| layout.placement).collect(),
+        };
+
+        let region = CaptureRegion::new(1400, 0, 100, 100);
+        assert_eq!(
+            frame.region_to_physical(region, CoordinateSpace::Logical),
+            CaptureRegion::new(2800, 0, 200, 200)
+        );
+    }
+
+    #[test]
+    fn stitched_region_is_converted_per_monitor() {
+        // A 2x monitor of 100x100 logical pixels with a 1x monitor to its right
+        let frame = CapturedFrame {
+            image: RgbaImage::new(300, 200),
+            scale_factor: 2.0,
+            monitors: vec![
+                MonitorPlacement {
+                    logical: CaptureRegion::new(0, 0, 100, 100),
+                    physical_x: 0,
+                    physical_y: 0,
+                    scale_factor: 2.0,
+                },
+                MonitorPlacement {
+                    logical: CaptureRegion::new(100, 0, 100, 100),
+                    physical_x: 200,
+                    physical_y: 0,
+                    scale_factor: 1.0,
+                },
+            ],
+        };
+
+        let region = CaptureRegion::new(50, 10, 100, 20);
+        assert_eq!(
+            frame.region_to_physical(region, CoordinateSpace::Logical),
+            CaptureRegion::new(100, 10, 150, 50)
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use crate::services::settings::{OutputConfig, OutputFormat, PngOptimization};
+use std::io::Cursor;
+use xcap::image::codecs::avif::AvifEncoder;
+use xcap::image::codecs::jpeg::JpegEncoder;
+use xcap::image::imageops::FilterType;
+use xcap::image::{DynamicImage, ImageFormat, ImageReader};
+
+/// AVIF encoder speed from 1 (slowest) to 10 (fastest). Screenshots are
+/// encoded interactively, so favour speed over the last few percent of size.
+const AVIF_SPEED: u8 = 8;
+
+/// Lowest quality the upload size cap will step down to before shrinking the image
+const MIN_UPLOAD_QUALITY: u8 = 40;
+const UPLOAD_QUALITY_STEP: u8 = 10;
+/// Factor applied to both sides each time the image has to shrink to fit the size cap
+const UPLOAD_DOWNSCALE_STEP: f64 = 0.8;
+/// Give up on the size cap rather than shrinking the longest side below this
+const MIN_UPLOAD_DIMENSION: u32 = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EncodeError {
+    #[error("Failed to decode image: {0}")]
+    DecodeFailed(String),
+    #[error("Failed to encode image as {0}: {1}")]
+    EncodeFailed(&'static str, String),
+    #[error("Image could not be reduced below {limit} bytes (smallest attempt was {size} bytes)")]
+    SizeLimitExceeded { size: u64, limit: u64 },
+}
+
+/// Size limits applied to images before they are uploaded
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UploadLimits {
+    pub max_dimension: Option<u32>,
+    pub max_file_size: Option<u64>,
+}
+
+/// Result of a PNG optimization pass
+pub struct OptimizedPng {
+    pub data: Vec<u8>,
+    pub bytes_saved: u64,
+}
+
+/// Decode an image of any supported format from memory
+pub fn decode_image(bytes: &[u8]) -> Result<DynamicImage, EncodeError> {
+    ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| EncodeError::DecodeFailed(e.to_string()))?
+        .decode()
+        .map_err(|e| EncodeError::DecodeFailed(e.to_string()))
+}
+
+/// Guess the format of encoded image bytes, if it is one we can write
+pub fn detect_format(bytes: &[u8]) -> Option<OutputFormat> {
+    match xcap::image::guess_format(bytes).ok()? {
+        ImageFormat::Png => Some(OutputFormat::Png),
+        ImageFormat::Jpeg => Some(OutputFormat::Jpeg),
+        ImageFormat::WebP => Some(OutputFormat::Webp),
+        ImageFormat::Avif => Some(OutputFormat::Avif),
+        _ => None,
+    }
+}
+
+/// Encode `image` using the configured output format and quality
+pub fn encode_image(image: &DynamicImage, config: &OutputConfig) -> Result<Vec<u8>, EncodeError> {
+    let quality = config.quality.clamp(1, 100);
+    let mut buffer = Vec::new();
+
+    match config.format {
+        OutputFormat::Png => {
+            image
+                .write_to(&mut Cursor::new(&mut buffer), ImageFormat::Png)
+                .map_err(|e| EncodeError::EncodeFailed("PNG", e.to_string()))?;
+        }
+        OutputFormat::Jpeg => {
+            // JPEG has no alpha channel
+            let rgb = DynamicImage::ImageRgb8(image.to_rgb8());
+            rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut buffer, quality))
+                .map_err(|e| EncodeError::EncodeFailed("JPEG", e.to_string()))?;
+        }
+        OutputFormat::Webp => {
+            let rgba = image.to_rgba8();
+            let encoded = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                .encode_simple(config.lossless, quality as f32)
+                .map_err(|e| EncodeError::EncodeFailed("WebP", format!("{:?}", e)))?;
+            buffer.extend_from_slice(&encoded);
+        }
+        OutputFormat::Avif => {
+            let quality = if config.lossless { 100 } else { quality };
+            let rgba = DynamicImage::ImageRgba8(image.to_rgba8());
+            rgba.write_with_encoder(AvifEncoder::new_with_speed_quality(
+                &mut buffer,
+                AVIF_SPEED,
+                quality,
+            ))
+            .map_err(|e| EncodeError::EncodeFailed("AVIF", e.to_string()))?;
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// Losslessly recompress PNG data by trying different filter and deflate
+/// strategies. Anything that isn't a PNG, or an `Off` level, is passed
+/// through untouched. The optimization is best-effort: if it fails the
+/// original data is returned so saving or uploading can still go ahead.
+pub fn optimize_png(data: Vec<u8>, level: PngOptimization) -> OptimizedPng {
+    let options = match level {
+        PngOptimization::Off => None,
+        PngOptimization::Fast => Some(oxipng::Options::from_preset(2)),
+        PngOptimization::Max => Some(oxipng::Options::max_compression()),
+    };
+
+    let Some(options) = options.filter(|_| detect_format(&data) == Some(OutputFormat::Png)) else {
+        return OptimizedPng {
+            data,
+            bytes_saved: 0,
+        };
+    };
+
+    match oxipng::optimize_from_memory(&data, &options) {
+        Ok(optimized) if optimized.len() < data.len() => OptimizedPng {
+            bytes_saved: (data.len() - optimized.len()) as u64,
+            data: optimized,
+        },
+        Ok(_) => OptimizedPng {
+            data,
+            bytes_saved: 0,
+        },
+        Err(e) => {
+            eprintln!("PNG optimization failed, using original image: {}", e);
+            OptimizedPng {
+                data,
+                bytes_saved: 0,
+            }
+        }
+    }
+}
+
+/// Shrink an encoded image so it satisfies `limits`, keeping its format.
+///
+/// The image is first resampled so its longest side fits `max_dimension`.
+/// While it is still larger than `max_file_size`, lossy formats step the
+/// encoder quality down to `MIN_UPLOAD_QUALITY`, after which (or straight
+/// away for PNG) the image is downscaled further. Data that already fits,
+/// or isn't an image we can re-encode, is returned unchanged.
+pub fn fit_for_upload(
+    data: Vec<u8>,
+    limits: &UploadLimits,
+    output: &OutputConfig,
+) -> Result<Vec<u8>, EncodeError> {
+    let max_dimension = limits.max_dimension.filter(|&max| max > 0);
+    let fits = |len: usize| limits.max_file_size.is_none_or(|max| len as u64 <= max);
+
+    if max_dimension.is_none() && fits(data.len()) {
+        return Ok(data);
+    }
+
+    let Some(format) = detect_format(&data) else {
+        return Ok(data);
+    };
+
+    let mut image = decode_image(&data)?;
+    let mut resized = false;
+
+    if let Some(max) = max_dimension {
+        if image.width() > max || image.height() > max {
+            image = image.resize(max, max, FilterType::Lanczos3);
+            resized = true;
+        }
+    }
+
+    if !resized && fits(data.len()) {
+        return Ok(data);
+    }
+
+    let mut config = OutputConfig {
+        format,
+        quality: output.quality.clamp(MIN_UPLOAD_QUALITY, 100),
+        lossless: output.lossless && matches!(format, OutputFormat::Webp | OutputFormat::Avif),
+        png_optimization: PngOptimization::Off,
+    };
+    let mut encoded = encode_image(&image, &config)?;
+
+    while !fits(encoded.len()) {
+        if config.lossless {
+            config.lossless = false;
+        } else if format != OutputFormat::Png && config.quality > MIN_UPLOAD_QUALITY {
+            config.quality = config
+                .quality
+                .saturating_sub(UPLOAD_QUALITY_STEP)
+                .max(MIN_UPLOAD_QUALITY);
+        } else {
+            let width = (image.width() as f64 * UPLOAD_DOWNSCALE_STEP) as u32;
+            let height = (image.height() as f64 * UPLOAD_DOWNSCALE_STEP) as u32;
+
+            if width.max(height) < MIN_UPLOAD_DIMENSION {
+                return Err(EncodeError::SizeLimitExceeded {
+                    size: encoded.len() as u64,
+                    limit: limits.max_file_size.unwrap_or_default(),
+                });
+            }
+
+            image = image.resize(width, height, FilterType::Lanczos3);
+        }
+
+        encoded = encode_image(&image, &config)?;
+    }
+
+    Ok(encoded)
+}
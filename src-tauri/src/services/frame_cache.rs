@@ -0,0 +1,55 @@
+use crate::services::capture::{CaptureError, CapturedFrame};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Frames grabbed the moment the capture shortcut fires, before the region
+/// selector is shown, so the overlay itself never ends up in the screenshot.
+///
+/// Only the frames of the latest capture are kept. Ids keep increasing across
+/// captures, so a stale id from an earlier capture is reported as missing
+/// instead of silently returning a newer frame.
+#[derive(Default)]
+pub struct FrameCache {
+    inner: Mutex<FrameCacheInner>,
+}
+
+#[derive(Default)]
+struct FrameCacheInner {
+    next_id: u32,
+    frames: HashMap<u32, Arc<CapturedFrame>>,
+}
+
+impl FrameCache {
+    fn lock(&self) -> MutexGuard<'_, FrameCacheInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Replace all cached frames with `frames`, returning their ids in order
+    pub fn replace(&self, frames: Vec<CapturedFrame>) -> Vec<u32> {
+        let mut inner = self.lock();
+        inner.frames.clear();
+
+        frames
+            .into_iter()
+            .map(|frame| {
+                inner.next_id = inner.next_id.wrapping_add(1);
+                let id = inner.next_id;
+                inner.frames.insert(id, Arc::new(frame));
+                id
+            })
+            .collect()
+    }
+
+    pub fn get(&self, id: u32) -> Result<Arc<CapturedFrame>, CaptureError> {
+        self.lock()
+            .frames
+            .get(&id)
+            .cloned()
+            .ok_or(CaptureError::FrameNotFound(id))
+    }
+
+    /// Drop all cached frames to free their memory
+    pub fn clear(&self) {
+        self.lock().frames.clear();
+    }
+}
@@ -0,0 +1,186 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+use ssh2::{CheckResult, HashType, HostKeyType, KnownHostFileKind, KnownHosts, Session};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HostKeyError {
+    #[error("Server did not provide a host key")]
+    MissingKey,
+    #[error(
+        "the key for {host} has changed (now {key_type} {fingerprint}). \
+         Someone could be intercepting the connection."
+    )]
+    Mismatch {
+        host: String,
+        key_type: &'static str,
+        fingerprint: String,
+    },
+    #[error("Host key for {host} ({key_type} {fingerprint}) was not trusted")]
+    NotTrusted {
+        host: String,
+        key_type: &'static str,
+        fingerprint: String,
+    },
+    #[error("Failed to check known hosts: {0}")]
+    KnownHostsFailed(String),
+}
+
+/// A host key seen for the first time, shown to the user for confirmation
+#[derive(Debug, Clone, Serialize)]
+pub struct UnknownHostKey {
+    pub host: String,
+    pub port: u16,
+    pub key_type: &'static str,
+    /// OpenSSH style `SHA256:...` fingerprint
+    pub fingerprint: String,
+}
+
+/// Path of the app's own known_hosts file, which keys trusted on first use
+/// are added to. `~/.ssh/known_hosts` is only ever read.
+fn app_known_hosts_path() -> Result<PathBuf, HostKeyError> {
+    let config_dir = dirs::config_dir().ok_or_else(|| {
+        HostKeyError::KnownHostsFailed("Failed to get config directory".to_string())
+    })?;
+
+    Ok(config_dir.join("shot-share").join("known_hosts"))
+}
+
+fn user_known_hosts_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".ssh").join("known_hosts"))
+}
+
+fn key_type_name(key_type: HostKeyType) -> &'static str {
+    match key_type {
+        HostKeyType::Rsa => "ssh-rsa",
+        HostKeyType::Dss => "ssh-dss",
+        HostKeyType::Ecdsa256 => "ecdsa-sha2-nistp256",
+        HostKeyType::Ecdsa384 => "ecdsa-sha2-nistp384",
+        HostKeyType::Ecdsa521 => "ecdsa-sha2-nistp521",
+        HostKeyType::Ed25519 => "ssh-ed25519",
+        HostKeyType::Unknown => "unknown",
+    }
+}
+
+/// known_hosts entries use `[host]:port` for anything but the default port
+fn host_entry_name(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+fn read_into(known_hosts: &mut KnownHosts, path: &Path) -> Result<(), HostKeyError> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    known_hosts
+        .read_file(path, KnownHostFileKind::OpenSSH)
+        .map(|_| ())
+        .map_err(|e| {
+            HostKeyError::KnownHostsFailed(format!("Failed to read '{}': {}", path.display(), e))
+        })
+}
+
+/// Check the server's host key against `~/.ssh/known_hosts` and the app's
+/// known_hosts file. An unknown key is passed to `confirm`, and saved to the
+/// app's file if the user trusts it. A changed key is always rejected.
+pub fn verify(
+    session: &Session,
+    host: &str,
+    port: u16,
+    confirm: impl FnOnce(&UnknownHostKey) -> bool,
+) -> Result<(), HostKeyError> {
+    let (key, key_type) = session.host_key().ok_or(HostKeyError::MissingKey)?;
+    let key_type_name = key_type_name(key_type);
+    let fingerprint = session
+        .host_key_hash(HashType::Sha256)
+        .map(|hash| format!("SHA256:{}", general_purpose::STANDARD_NO_PAD.encode(hash)))
+        .ok_or(HostKeyError::MissingKey)?;
+
+    let new_known_hosts = || {
+        session
+            .known_hosts()
+            .map_err(|e| HostKeyError::KnownHostsFailed(e.to_string()))
+    };
+    let app_path = app_known_hosts_path()?;
+
+    let mut known_hosts = new_known_hosts()?;
+    if let Some(user_path) = user_known_hosts_path() {
+        // A malformed personal known_hosts shouldn't block uploads entirely
+        if let Err(e) = read_into(&mut known_hosts, &user_path) {
+            eprintln!("{}", e);
+        }
+    }
+    read_into(&mut known_hosts, &app_path)?;
+
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(HostKeyError::Mismatch {
+            host: host_entry_name(host, port),
+            key_type: key_type_name,
+            fingerprint,
+        }),
+        CheckResult::Failure => Err(HostKeyError::KnownHostsFailed(format!(
+            "Could not check host key for {}",
+            host_entry_name(host, port)
+        ))),
+        CheckResult::NotFound => {
+            let unknown = UnknownHostKey {
+                host: host.to_string(),
+                port,
+                key_type: key_type_name,
+                fingerprint,
+            };
+
+            if !confirm(&unknown) {
+                return Err(HostKeyError::NotTrusted {
+                    host: host_entry_name(host, port),
+                    key_type: unknown.key_type,
+                    fingerprint: unknown.fingerprint,
+                });
+            }
+
+            // Re-read only the app file so entries from ~/.ssh aren't copied into it
+            let mut app_known_hosts = new_known_hosts()?;
+            read_into(&mut app_known_hosts, &app_path)?;
+            app_known_hosts
+                .add(
+                    &host_entry_name(host, port),
+                    key,
+                    "added by shot-share",
+                    key_type.into(),
+                )
+                .map_err(|e| HostKeyError::KnownHostsFailed(e.to_string()))?;
+
+            if let Some(parent) = app_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| {
+                    HostKeyError::KnownHostsFailed(format!(
+                        "Failed to create config directory: {}",
+                        e
+                    ))
+                })?;
+            }
+            app_known_hosts
+                .write_file(&app_path, KnownHostFileKind::OpenSSH)
+                .map_err(|e| {
+                    HostKeyError::KnownHostsFailed(format!(
+                        "Failed to save '{}': {}",
+                        app_path.display(),
+                        e
+                    ))
+                })?;
+
+            println!(
+                "Trusted new host key for {} ({} {})",
+                host_entry_name(host, port),
+                unknown.key_type,
+                unknown.fingerprint
+            );
+            Ok(())
+        }
+    }
+}
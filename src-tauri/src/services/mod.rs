@@ -1,5 +1,30 @@
+pub mod active_uploads;
+pub mod auth_prompt;
+pub mod capture;
+pub mod encoding;
+pub mod frame_cache;
+pub mod known_hosts;
+pub mod remote_name;
+pub mod remote_path;
+pub mod session_manager;
 pub mod settings;
 pub mod sftp;
+pub mod upload_history;
+pub mod upload_queue;
 
-pub use settings::Settings;
-pub use sftp::SftpUploader;
+pub use active_uploads::ActiveUploads;
+pub use auth_prompt::AuthPrompts;
+pub use capture::{CaptureRegion, CaptureSize, CoordinateSpace, MonitorInfo, WindowInfo};
+pub use frame_cache::FrameCache;
+pub use known_hosts::UnknownHostKey;
+pub use session_manager::SessionManager;
+pub use settings::{
+    CollisionPolicy, NamingStrategy, OutputConfig, OutputFormat, PngOptimization, RetryConfig,
+    Settings, SftpAuthMethod,
+};
+pub use sftp::{
+    AuthChallenge, AuthPrompter, RemoteEntry, RemoteModes, SftpAuth, SftpError, SftpUploader,
+    UploadProgress, UploadResult,
+};
+pub use upload_history::{UploadHistory, UploadHistoryPage, UploadRecord};
+pub use upload_queue::{QueuedUpload, QueuedUploadStatus, UploadQueue, UploadTarget};
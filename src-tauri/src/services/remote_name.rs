@@ -0,0 +1,75 @@
+use crate::services::settings::NamingStrategy;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::io::{self, Read};
+
+/// Shortest random slug accepted, anything shorter is too easy to guess
+const MIN_SLUG_LENGTH: usize = 6;
+const MAX_SLUG_LENGTH: usize = 64;
+/// Hex characters of the SHA-256 digest used for content hash names
+const HASH_NAME_LENGTH: usize = 32;
+
+/// Produces candidate remote file names for one upload
+pub struct RemoteNamer {
+    strategy: NamingStrategy,
+    /// Name without extension for the deterministic strategies
+    stem: String,
+    /// Extension including the leading dot, or empty
+    extension: String,
+}
+
+impl RemoteNamer {
+    /// `original` is the name the file would get with the timestamp strategy,
+    /// `content_hash` the `sha256_hex` of the file
+    pub fn new(strategy: NamingStrategy, original: &str, content_hash: &str) -> Self {
+        let (stem, extension) = match original.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), format!(".{}", ext)),
+            _ => (original.to_string(), String::new()),
+        };
+
+        let stem = match strategy {
+            NamingStrategy::ContentHash => content_hash[..HASH_NAME_LENGTH].to_string(),
+            _ => stem,
+        };
+
+        Self {
+            strategy,
+            stem,
+            extension,
+        }
+    }
+
+    /// Name to try on the given attempt, starting at 0. Random names are
+    /// regenerated, the other strategies get a numbered suffix.
+    pub fn candidate(&self, attempt: u32) -> String {
+        match self.strategy {
+            NamingStrategy::Random { length } => {
+                format!("{}{}", random_slug(length), self.extension)
+            }
+            _ if attempt == 0 => format!("{}{}", self.stem, self.extension),
+            _ => format!("{}-{}{}", self.stem, attempt, self.extension),
+        }
+    }
+}
+
+fn random_slug(length: usize) -> String {
+    // thread_rng is a CSPRNG seeded from the OS, so slugs can't be predicted
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length.clamp(MIN_SLUG_LENGTH, MAX_SLUG_LENGTH))
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect()
+}
+
+/// Lowercase hex SHA-256 of everything `reader` yields
+pub fn sha256_hex(reader: &mut dyn Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
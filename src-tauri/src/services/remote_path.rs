@@ -0,0 +1,181 @@
+use chrono::{DateTime, Local};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RemotePathError {
+    #[error("Unknown placeholder '{{{0}}}' in remote path")]
+    UnknownPlaceholder(String),
+    #[error("Unclosed '{{' in remote path")]
+    UnclosedPlaceholder,
+}
+
+/// Values substituted into a remote path template
+pub struct PathContext {
+    pub now: DateTime<Local>,
+    pub profile: String,
+}
+
+impl PathContext {
+    pub fn new(profile: &str) -> Self {
+        Self {
+            now: Local::now(),
+            profile: profile.to_string(),
+        }
+    }
+}
+
+/// Expand the placeholders in a remote path template:
+///
+/// - `{yyyy}`, `{mm}`, `{dd}`: local date of the upload
+/// - `{user}`: name of the logged in user on this machine
+/// - `{hostname}`: name of this machine
+/// - `{profile}`: name of the upload profile
+///
+/// Substituted values never introduce extra path segments.
+pub fn expand(template: &str, context: &PathContext) -> Result<String, RemotePathError> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+
+        let end = rest[start..]
+            .find('}')
+            .ok_or(RemotePathError::UnclosedPlaceholder)?;
+        let name = &rest[start + 1..start + end];
+
+        let value = match name {
+            "yyyy" => context.now.format("%Y").to_string(),
+            "mm" => context.now.format("%m").to_string(),
+            "dd" => context.now.format("%d").to_string(),
+            "user" => whoami::fallible::username().unwrap_or_else(|_| "unknown".to_string()),
+            "hostname" => whoami::fallible::hostname().unwrap_or_else(|_| "unknown".to_string()),
+            "profile" => context.profile.clone(),
+            _ => return Err(RemotePathError::UnknownPlaceholder(name.to_string())),
+        };
+        expanded.push_str(&sanitize_segment(&value));
+
+        rest = &rest[start + end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// The leading directories of `template` that contain no placeholders. The
+/// configured base URL points at this directory.
+pub fn static_prefix(template: &str) -> &str {
+    match template.find('{') {
+        Some(brace) => match template[..brace].rfind('/') {
+            Some(slash) => &template[..slash],
+            None => "",
+        },
+        None => template.trim_end_matches('/'),
+    }
+}
+
+/// Path of an uploaded file relative to the static prefix of `template`,
+/// i.e. what to append to the base URL
+pub fn url_path(template: &str, remote_file_path: &str) -> String {
+    remote_file_path
+        .strip_prefix(static_prefix(template))
+        .unwrap_or(remote_file_path)
+        .trim_start_matches('/')
+        .to_string()
+}
+
+fn sanitize_segment(value: &str) -> String {
+    let sanitized: String = value
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    match sanitized.as_str() {
+        "" | "." | ".." => "_".to_string(),
+        _ => sanitized,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn context(profile: &str) -> PathContext {
+        PathContext {
+            now: Local.with_ymd_and_hms(2024, 3, 5, 12, 0, 0).unwrap(),
+            profile: profile.to_string(),
+        }
+    }
+
+    #[test]
+    fn expands_date_placeholders() {
+        let expanded = expand("/srv/shots/{yyyy}/{mm}/{dd}", &context("default")).unwrap();
+        assert_eq!(expanded, "/srv/shots/2024/03/05");
+    }
+
+    #[test]
+    fn expands_placeholder_mid_segment() {
+        let expanded = expand("/srv/shots-{yyyy}{mm}/x", &context("default")).unwrap();
+        assert_eq!(expanded, "/srv/shots-202403/x");
+    }
+
+    #[test]
+    fn template_without_placeholders_is_unchanged() {
+        let expanded = expand("/srv/shots/", &context("default")).unwrap();
+        assert_eq!(expanded, "/srv/shots/");
+    }
+
+    #[test]
+    fn rejects_unknown_placeholder() {
+        let err = expand("/srv/{month}", &context("default")).unwrap_err();
+        assert!(matches!(err, RemotePathError::UnknownPlaceholder(name) if name == "month"));
+    }
+
+    #[test]
+    fn rejects_unclosed_placeholder() {
+        let err = expand("/srv/{yyyy/x", &context("default")).unwrap_err();
+        assert!(matches!(err, RemotePathError::UnclosedPlaceholder));
+    }
+
+    #[test]
+    fn substituted_values_stay_in_one_segment() {
+        assert_eq!(
+            expand("/srv/{profile}", &context("a/../b")).unwrap(),
+            "/srv/a_.._b"
+        );
+        assert_eq!(
+            expand("/srv/{profile}", &context("..\\x")).unwrap(),
+            "/srv/.._x"
+        );
+        assert_eq!(expand("/srv/{profile}", &context("..")).unwrap(), "/srv/_");
+        assert_eq!(expand("/srv/{profile}", &context(" ")).unwrap(), "/srv/_");
+    }
+
+    #[test]
+    fn static_prefix_stops_before_first_placeholder_segment() {
+        assert_eq!(static_prefix("/srv/shots/{yyyy}/{mm}"), "/srv/shots");
+        assert_eq!(static_prefix("/srv/shots-{yyyy}/x"), "/srv");
+        assert_eq!(static_prefix("{yyyy}/x"), "");
+        assert_eq!(static_prefix("/srv/shots/"), "/srv/shots");
+        assert_eq!(static_prefix("/"), "");
+    }
+
+    #[test]
+    fn url_path_is_relative_to_static_prefix() {
+        assert_eq!(
+            url_path("/srv/shots/{yyyy}/{mm}", "/srv/shots/2024/03/a.png"),
+            "2024/03/a.png"
+        );
+        assert_eq!(
+            url_path("/srv/shots-{yyyy}", "/srv/shots-2024/a.png"),
+            "shots-2024/a.png"
+        );
+        assert_eq!(url_path("/srv/shots/", "/srv/shots/a.png"), "a.png");
+        assert_eq!(url_path("/", "/a.png"), "a.png");
+    }
+}
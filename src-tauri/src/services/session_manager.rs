@@ -0,0 +1,134 @@
+use ssh2::{Session, Sftp};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Interval libssh2 is told to send keepalives at, and the reaper thread
+/// checks idle sessions at
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Identifies which server and account a session is authenticated for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionKey {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+}
+
+/// An authenticated session with its SFTP channel already open
+pub struct PooledSession {
+    pub session: Session,
+    pub sftp: Sftp,
+}
+
+struct IdleSession {
+    pooled: PooledSession,
+    idle_since: Instant,
+    idle_timeout: Duration,
+}
+
+/// Keeps authenticated SSH sessions alive between uploads so a burst of
+/// uploads doesn't pay for a TCP connect, handshake and auth each time.
+///
+/// Sessions are checked out with `take` and handed back with `put` once an
+/// operation succeeded, so a session that failed is simply dropped. A
+/// background thread sends keepalives on idle sessions and closes them once
+/// they've been idle longer than their timeout.
+#[derive(Clone, Default)]
+pub struct SessionManager {
+    inner: Arc<Mutex<HashMap<SessionKey, IdleSession>>>,
+    reaper_started: Arc<AtomicBool>,
+    /// Bumped by `clear`, so the reaper doesn't hand back sessions that were
+    /// cleared while it was sending keepalives
+    generation: Arc<AtomicU64>,
+}
+
+impl SessionManager {
+    fn lock(&self) -> MutexGuard<'_, HashMap<SessionKey, IdleSession>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Check out the idle session for `key`, if there is one that hasn't
+    /// timed out
+    pub fn take(&self, key: &SessionKey) -> Option<PooledSession> {
+        let idle = self.lock().remove(key)?;
+
+        if idle.idle_since.elapsed() >= idle.idle_timeout {
+            return None;
+        }
+
+        Some(idle.pooled)
+    }
+
+    /// Hand a healthy session back to be reused for up to `idle_timeout`.
+    /// A zero timeout closes it straight away.
+    pub fn put(&self, key: SessionKey, pooled: PooledSession, idle_timeout: Duration) {
+        if idle_timeout.is_zero() {
+            return;
+        }
+
+        self.lock().insert(
+            key,
+            IdleSession {
+                pooled,
+                idle_since: Instant::now(),
+                idle_timeout,
+            },
+        );
+        self.start_reaper();
+    }
+
+    /// Close all idle sessions, e.g. after the connection settings changed
+    pub fn clear(&self) {
+        let mut sessions = self.lock();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        sessions.clear();
+    }
+
+    fn start_reaper(&self) {
+        if self.reaper_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let manager = self.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(KEEPALIVE_INTERVAL);
+
+            // Keepalives block on the network, so send them with the sessions
+            // taken out of the pool rather than while holding the lock
+            let generation = manager.generation.load(Ordering::SeqCst);
+            let idle_sessions = std::mem::take(&mut *manager.lock());
+
+            let live: Vec<_> = idle_sessions
+                .into_iter()
+                .filter(|(key, idle)| {
+                    if idle.idle_since.elapsed() >= idle.idle_timeout {
+                        println!("Closing idle SFTP session to {}@{}", key.username, key.host);
+                        return false;
+                    }
+
+                    match idle.pooled.session.keepalive_send() {
+                        Ok(_) => true,
+                        Err(e) => {
+                            eprintln!(
+                                "SFTP session to {}@{} dropped: {}",
+                                key.username, key.host, e
+                            );
+                            false
+                        }
+                    }
+                })
+                .collect();
+
+            let mut sessions = manager.lock();
+            // Sessions closed by `clear` in the meantime stay closed, and a
+            // session handed back meanwhile is newer than ours
+            if manager.generation.load(Ordering::SeqCst) == generation {
+                for (key, idle) in live {
+                    sessions.entry(key).or_insert(idle);
+                }
+            }
+        });
+    }
+}
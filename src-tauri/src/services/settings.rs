@@ -5,6 +5,55 @@ use std::path::PathBuf;
 
 const KEYRING_SERVICE: &str = "shot-share";
 const KEYRING_USERNAME: &str = "sftp-password";
+const KEYRING_PASSPHRASE_USERNAME: &str = "sftp-key-passphrase";
+/// Prefix of the keyring entries holding pasted private keys, suffixed with
+/// the key's position among the `KeyData` methods
+const KEYRING_PRIVATE_KEY_PREFIX: &str = "sftp-private-key";
+/// Stored in settings.json in place of a private key or key passphrase kept
+/// in the keyring
+const PRIVATE_KEY_IN_KEYRING: &str = "<keyring>";
+
+/// How the SFTP uploader authenticates with the server
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SftpAuthMethod {
+    /// Identities loaded in the running ssh-agent
+    Agent,
+    #[default]
+    Password,
+    /// Answer whatever the server asks, e.g. a one-time code, through the
+    /// frontend prompt
+    KeyboardInteractive,
+    /// OpenSSH or PEM private key read from disk
+    KeyFile { path: String },
+    /// Private key contents pasted into the settings. The key itself lives
+    /// in the OS keyring, settings.json only keeps a placeholder.
+    KeyData { private_key: String },
+}
+
+/// How uploaded files are named on the server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NamingStrategy {
+    /// Keep the local `screenshot_<timestamp>` file name
+    #[default]
+    Timestamp,
+    /// Random, hard to guess slug of `length` characters
+    Random { length: usize },
+    /// Hash of the uploaded content
+    ContentHash,
+}
+
+/// What to do when a file with the chosen name already exists remotely
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionPolicy {
+    /// Pick a different name and try again
+    #[default]
+    Rename,
+    /// Fail the upload
+    Refuse,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SftpConfig {
@@ -13,17 +62,64 @@ pub struct SftpConfig {
     pub username: String,
     #[serde(default)]
     pub password: String,
+    /// Authentication methods, tried in order until one succeeds
+    #[serde(default = "default_auth_methods")]
+    pub auth_methods: Vec<SftpAuthMethod>,
+    /// Passphrase for an encrypted private key, empty if the key is
+    /// unencrypted. Kept in the OS keyring, settings.json only keeps a
+    /// placeholder.
+    #[serde(default)]
+    pub key_passphrase: String,
+    /// Remote directory, which may contain placeholders such as
+    /// `{yyyy}/{mm}/{dd}`, `{user}`, `{hostname}` and `{profile}`
     pub remote_path: String,
+    /// Permission bits set on uploaded files, e.g. 0o644
+    #[serde(default)]
+    pub file_mode: Option<u32>,
+    /// Permission bits set on directories created for uploads, e.g. 0o755
+    #[serde(default)]
+    pub dir_mode: Option<u32>,
+    #[serde(default)]
+    pub naming: NamingStrategy,
+    #[serde(default)]
+    pub collision_policy: CollisionPolicy,
+    /// Name of this upload profile, substituted for `{profile}`
+    #[serde(default = "default_profile_name")]
+    pub profile_name: String,
     #[serde(default = "default_base_url")]
     pub base_url: String,
     #[serde(default = "default_copy_to_clipboard")]
     pub copy_to_clipboard: bool,
+    /// Keep the SSH session open for this many seconds after an upload so
+    /// the next one can reuse it. Zero reconnects for every upload.
+    #[serde(default = "default_session_idle_timeout")]
+    pub session_idle_timeout_secs: u64,
+    /// Downscale uploads so neither side exceeds this many pixels
+    #[serde(default)]
+    pub max_dimension: Option<u32>,
+    /// Re-encode uploads until they are at most this many bytes
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+    #[serde(default)]
+    pub retry: RetryConfig,
+}
+
+fn default_profile_name() -> String {
+    String::from("default")
 }
 
 fn default_base_url() -> String {
     String::from("https://example.com")
 }
 
+fn default_auth_methods() -> Vec<SftpAuthMethod> {
+    vec![SftpAuthMethod::Password]
+}
+
+fn default_session_idle_timeout() -> u64 {
+    300
+}
+
 fn default_copy_to_clipboard() -> bool {
     true
 }
@@ -35,9 +131,133 @@ impl Default for SftpConfig {
             port: 22,
             username: String::new(),
             password: String::new(),
+            auth_methods: default_auth_methods(),
+            key_passphrase: String::new(),
             remote_path: String::from("/uploads"),
+            file_mode: None,
+            dir_mode: None,
+            naming: NamingStrategy::Timestamp,
+            collision_policy: CollisionPolicy::Rename,
+            profile_name: default_profile_name(),
             base_url: String::from("https://example.com"),
             copy_to_clipboard: true,
+            session_idle_timeout_secs: default_session_idle_timeout(),
+            max_dimension: None,
+            max_file_size: None,
+            retry: RetryConfig::default(),
+        }
+    }
+}
+
+/// How often and how patiently uploads are retried after a dropped
+/// connection or timeout
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// Retries after the first attempt, zero disables retrying
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry
+    #[serde(default = "default_initial_delay_ms")]
+    pub initial_delay_ms: u64,
+    /// Upper bound for the delay between two attempts
+    #[serde(default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// Factor the delay grows by after each retry
+    #[serde(default = "default_backoff_multiplier")]
+    pub multiplier: f64,
+    /// Fraction of each delay that is randomized, from 0 to 1
+    #[serde(default = "default_jitter")]
+    pub jitter: f64,
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_initial_delay_ms() -> u64 {
+    500
+}
+
+fn default_max_delay_ms() -> u64 {
+    10_000
+}
+
+fn default_backoff_multiplier() -> f64 {
+    2.0
+}
+
+fn default_jitter() -> f64 {
+    0.5
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            initial_delay_ms: default_initial_delay_ms(),
+            max_delay_ms: default_max_delay_ms(),
+            multiplier: default_backoff_multiplier(),
+            jitter: default_jitter(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+    Avif,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+        }
+    }
+}
+
+/// Lossless recompression pass applied to PNG files before saving and uploading
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PngOptimization {
+    #[default]
+    Off,
+    Fast,
+    Max,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputConfig {
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// Encoder quality from 1 to 100, used by lossy formats
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    /// Encode WebP and AVIF losslessly. PNG is always lossless and JPEG never is.
+    #[serde(default)]
+    pub lossless: bool,
+    #[serde(default)]
+    pub png_optimization: PngOptimization,
+}
+
+fn default_quality() -> u8 {
+    85
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::Png,
+            quality: 85,
+            lossless: false,
+            png_optimization: PngOptimization::Off,
         }
     }
 }
@@ -48,6 +268,8 @@ pub struct Settings {
     pub screenshot_shortcut: String,
     #[serde(default)]
     pub filename_prefix: String,
+    #[serde(default)]
+    pub output: OutputConfig,
     pub sftp: SftpConfig,
 }
 
@@ -61,56 +283,150 @@ impl Default for Settings {
                 .to_string(),
             screenshot_shortcut: String::from("CommandOrControl+Shift+S"),
             filename_prefix: String::from(""),
+            output: OutputConfig::default(),
             sftp: SftpConfig::default(),
         }
     }
 }
 
 impl Settings {
-    /// Get the keyring entry for SFTP password
-    fn get_keyring_entry() -> Result<Entry, String> {
+    /// Get the keyring entry for an SFTP secret
+    fn get_keyring_entry(account: &str) -> Result<Entry, String> {
         println!(
             "Getting keyring entry: service='{}', username='{}'",
-            KEYRING_SERVICE, KEYRING_USERNAME
+            KEYRING_SERVICE, account
         );
-        Entry::new(KEYRING_SERVICE, KEYRING_USERNAME)
-            .map_err(|e| format!("Failed to access keyring: {}", e))
+        Entry::new(KEYRING_SERVICE, account).map_err(|e| format!("Failed to access keyring: {}", e))
     }
 
-    /// Save SFTP password to OS keyring
-    fn save_password_to_keyring(password: &str) -> Result<(), String> {
+    /// Save an SFTP secret to OS keyring
+    fn save_secret_to_keyring(account: &str, secret: &str) -> Result<(), String> {
         println!(
-            "[save_password_to_keyring] Starting password save (length: {})",
-            password.len()
+            "[save_secret_to_keyring] Starting save of '{}' (length: {})",
+            account,
+            secret.len()
         );
-        let entry = Self::get_keyring_entry()?;
+        let entry = Self::get_keyring_entry(account)?;
         entry
-            .set_password(password)
-            .map_err(|e| format!("Failed to save password to keyring: {}", e))?;
-        println!("[save_password_to_keyring] Password saved successfully");
+            .set_password(secret)
+            .map_err(|e| format!("Failed to save {} to keyring: {}", account, e))?;
+        println!("[save_secret_to_keyring] '{}' saved successfully", account);
 
         // Immediately verify it was saved
         match entry.get_password() {
             Ok(retrieved) => {
                 println!(
-                    "[save_password_to_keyring] Verification: password retrieved (length: {})",
+                    "[save_secret_to_keyring] Verification: '{}' retrieved (length: {})",
+                    account,
                     retrieved.len()
                 );
             }
             Err(e) => {
-                eprintln!("[save_password_to_keyring] Verification failed: {:?}", e);
+                eprintln!("[save_secret_to_keyring] Verification failed: {:?}", e);
             }
         }
 
         Ok(())
     }
 
-    /// Delete SFTP password from OS keyring
-    fn delete_password_from_keyring() -> Result<(), String> {
-        let entry = Self::get_keyring_entry()?;
+    /// Delete an SFTP secret from OS keyring
+    fn delete_secret_from_keyring(account: &str) -> Result<(), String> {
+        let entry = Self::get_keyring_entry(account)?;
         entry
             .delete_credential()
-            .map_err(|e| format!("Failed to delete password from keyring: {}", e))
+            .map_err(|e| format!("Failed to delete {} from keyring: {}", account, e))
+    }
+
+    /// Store `secret` under `account`, or remove the entry when it is empty
+    fn sync_secret_to_keyring(account: &str, secret: &str) -> Result<(), String> {
+        if !secret.is_empty() {
+            Self::save_secret_to_keyring(account, secret)
+        } else {
+            println!("'{}' is empty, deleting from keyring", account);
+            // Ignore errors if no entry exists
+            let _ = Self::delete_secret_from_keyring(account);
+            Ok(())
+        }
+    }
+
+    fn private_key_account(index: usize) -> String {
+        format!("{}-{}", KEYRING_PRIVATE_KEY_PREFIX, index)
+    }
+
+    /// Move pasted private keys into the keyring, replacing them with a
+    /// placeholder, and drop entries of keys that were removed
+    fn store_private_keys(&mut self) -> Result<(), String> {
+        let mut index = 0;
+
+        for method in &mut self.sftp.auth_methods {
+            if let SftpAuthMethod::KeyData { private_key } = method {
+                let account = Self::private_key_account(index);
+                if *private_key != PRIVATE_KEY_IN_KEYRING {
+                    Self::sync_secret_to_keyring(&account, private_key)?;
+                    if !private_key.is_empty() {
+                        *private_key = PRIVATE_KEY_IN_KEYRING.to_string();
+                    }
+                }
+                index += 1;
+            }
+        }
+
+        while Self::delete_secret_from_keyring(&Self::private_key_account(index)).is_ok() {
+            index += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Read an SFTP secret from the keyring, logging and falling back to an
+    /// empty string if it can't be read
+    fn read_secret_from_keyring(account: &str) -> String {
+        Self::get_keyring_entry(account)
+            .and_then(|entry| {
+                entry
+                    .get_password()
+                    .map_err(|e| format!("Failed to read {} from keyring: {}", account, e))
+            })
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                String::new()
+            })
+    }
+
+    /// Move the key passphrase into the keyring, replacing it with a
+    /// placeholder
+    fn store_key_passphrase(&mut self) -> Result<(), String> {
+        let passphrase = &mut self.sftp.key_passphrase;
+        if *passphrase != PRIVATE_KEY_IN_KEYRING {
+            Self::sync_secret_to_keyring(KEYRING_PASSPHRASE_USERNAME, passphrase)?;
+            if !passphrase.is_empty() {
+                *passphrase = PRIVATE_KEY_IN_KEYRING.to_string();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replace the key passphrase placeholder with the stored passphrase
+    fn restore_key_passphrase(&mut self) {
+        if self.sftp.key_passphrase == PRIVATE_KEY_IN_KEYRING {
+            self.sftp.key_passphrase = Self::read_secret_from_keyring(KEYRING_PASSPHRASE_USERNAME);
+        }
+    }
+
+    /// Replace private key placeholders with the keys stored in the keyring
+    fn restore_private_keys(&mut self) {
+        let mut index = 0;
+
+        for method in &mut self.sftp.auth_methods {
+            if let SftpAuthMethod::KeyData { private_key } = method {
+                if *private_key == PRIVATE_KEY_IN_KEYRING {
+                    *private_key =
+                        Self::read_secret_from_keyring(&Self::private_key_account(index));
+                }
+                index += 1;
+            }
+        }
     }
 
     /// Get the path to the settings file
@@ -143,8 +459,10 @@ impl Settings {
         let contents = fs::read_to_string(&settings_path)
             .map_err(|e| format!("Failed to read settings file: {}", e))?;
 
-        let settings: Settings = serde_json::from_str(&contents)
+        let mut settings: Settings = serde_json::from_str(&contents)
             .map_err(|e| format!("Failed to parse settings: {}", e))?;
+        settings.restore_private_keys();
+        settings.restore_key_passphrase();
 
         Ok(settings)
     }
@@ -179,19 +497,13 @@ impl Settings {
         self.ensure_save_directory_exists()?;
 
         // Save password to keyring (only if not empty)
-        if !self.sftp.password.is_empty() {
-            println!(
-                "Saving password to keyring (length: {})",
-                self.sftp.password.len()
-            );
-            Self::save_password_to_keyring(&self.sftp.password)?;
-        } else {
-            println!("Password is empty, deleting from keyring");
-            // If password is empty, delete it from keyring
-            let _ = Self::delete_password_from_keyring(); // Ignore errors if no password exists
-        }
+        Self::sync_secret_to_keyring(KEYRING_USERNAME, &self.sftp.password)?;
+
+        let mut stored = self.clone();
+        stored.store_key_passphrase()?;
+        stored.store_private_keys()?;
 
-        let json = serde_json::to_string_pretty(self)
+        let json = serde_json::to_string_pretty(&stored)
             .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
         fs::write(&settings_path, json)
@@ -1,30 +1,518 @@
-use ssh2::Session;
+use crate::services::encoding::{self, UploadLimits};
+use crate::services::known_hosts::{self, HostKeyError, UnknownHostKey};
+use crate::services::remote_name::{self, RemoteNamer};
+use crate::services::remote_path::{self, PathContext};
+use crate::services::session_manager::{
+    PooledSession, SessionKey, SessionManager, KEEPALIVE_INTERVAL,
+};
+use crate::services::settings::{
+    CollisionPolicy, NamingStrategy, OutputConfig, PngOptimization, RetryConfig, SftpAuthMethod,
+};
+use rand::Rng;
+use serde::Serialize;
+use ssh2::{ErrorCode, FileStat, KeyboardInteractivePrompt, Prompt, RenameFlags, Session, Sftp};
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufReader, Read, Write};
 use std::net::{TcpStream, ToSocketAddrs};
-use std::path::Path;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Size of each chunk written to the remote file
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+/// Minimum time between two `upload-progress` reports
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Permissions of remote directories created for templated paths when no
+/// directory mode is configured
+const DEFAULT_DIR_MODE: u32 = 0o755;
+
+/// SFTP status code for a path that doesn't exist
+const LIBSSH2_FX_NO_SUCH_FILE: i32 = 2;
+/// SFTP status codes for a channel that went away
+const LIBSSH2_FX_NO_CONNECTION: i32 = 6;
+const LIBSSH2_FX_CONNECTION_LOST: i32 = 7;
+
+/// libssh2 session error codes for a connection that timed out or broke
+const LIBSSH2_ERROR_BANNER_RECV: i32 = -2;
+const LIBSSH2_ERROR_BANNER_SEND: i32 = -3;
+const LIBSSH2_ERROR_SOCKET_SEND: i32 = -7;
+const LIBSSH2_ERROR_TIMEOUT: i32 = -9;
+const LIBSSH2_ERROR_SOCKET_DISCONNECT: i32 = -13;
+const LIBSSH2_ERROR_SOCKET_TIMEOUT: i32 = -30;
+const LIBSSH2_ERROR_SOCKET_RECV: i32 = -43;
+
+/// Interval a retry delay is slept in, so cancelling stays responsive
+const RETRY_SLEEP_STEP: Duration = Duration::from_millis(100);
+/// Names tried before giving up when every candidate is already taken
+const MAX_NAME_ATTEMPTS: u32 = 5;
 
 #[derive(Debug, thiserror::Error)]
 pub enum SftpError {
     #[error("Failed to connect to SFTP server: {0}")]
     ConnectionFailed(String),
+    #[error("Connection to SFTP server was lost: {0}")]
+    ConnectionReset(String),
+    #[error("SFTP server did not respond in time: {0}")]
+    Timeout(String),
+    #[error("Could not resolve SFTP host: {0}")]
+    DnsFailed(String),
+    #[error("SFTP server is unreachable: {0}")]
+    Unreachable(String),
+    #[error("Host key mismatch: {0}")]
+    HostKeyMismatch(String),
+    #[error("Host key verification failed: {0}")]
+    HostKeyVerificationFailed(String),
     #[error("Authentication failed: {0}")]
     AuthenticationFailed(String),
+    #[error(
+        "Authentication failed - {}. Server accepts: {}",
+        .failures.join("; "),
+        .accepted.join(", ")
+    )]
+    AuthMethodsRejected {
+        accepted: Vec<String>,
+        failures: Vec<String>,
+    },
     #[error("Failed to read file: {0}")]
     FileReadError(String),
+    #[error("Failed to prepare image for upload: {0}")]
+    ImageProcessingFailed(String),
     #[error("Failed to upload file: {0}")]
     UploadFailed(String),
+    #[error("Remote operation failed: {0}")]
+    RemoteOperationFailed(String),
+    #[error("Upload was cancelled")]
+    Cancelled,
+    #[error("A file named '{0}' already exists on the server")]
+    RemoteFileExists(String),
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
 }
 
+impl SftpError {
+    /// Network problems that may go away on their own, so the operation is
+    /// worth retrying. Authentication and configuration errors never are.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            SftpError::ConnectionReset(_)
+                | SftpError::Timeout(_)
+                | SftpError::DnsFailed(_)
+                | SftpError::Unreachable(_)
+        )
+    }
+
+    /// Errors that mean the connection itself is unusable
+    fn is_connection_error(&self) -> bool {
+        self.is_transient() || matches!(self, SftpError::ConnectionFailed(_))
+    }
+
+    /// Classify a libssh2 error, using `other` when it isn't a network problem
+    fn from_ssh(e: &ssh2::Error, message: String, other: fn(String) -> SftpError) -> Self {
+        match e.code() {
+            ErrorCode::Session(LIBSSH2_ERROR_TIMEOUT | LIBSSH2_ERROR_SOCKET_TIMEOUT) => {
+                SftpError::Timeout(message)
+            }
+            ErrorCode::Session(
+                LIBSSH2_ERROR_BANNER_RECV
+                | LIBSSH2_ERROR_BANNER_SEND
+                | LIBSSH2_ERROR_SOCKET_SEND
+                | LIBSSH2_ERROR_SOCKET_DISCONNECT
+                | LIBSSH2_ERROR_SOCKET_RECV,
+            )
+            | ErrorCode::SFTP(LIBSSH2_FX_NO_CONNECTION | LIBSSH2_FX_CONNECTION_LOST) => {
+                SftpError::ConnectionReset(message)
+            }
+            _ => other(message),
+        }
+    }
+
+    /// Classify an I/O error from the socket or a remote file, using `other`
+    /// when it isn't a network problem
+    fn from_io(e: &std::io::Error, message: String, other: fn(String) -> SftpError) -> Self {
+        use std::io::ErrorKind;
+
+        match e.kind() {
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => SftpError::Timeout(message),
+            ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe
+            | ErrorKind::UnexpectedEof => SftpError::ConnectionReset(message),
+            // The server is down or the network (e.g. a VPN) isn't up yet
+            ErrorKind::ConnectionRefused
+            | ErrorKind::HostUnreachable
+            | ErrorKind::NetworkUnreachable => SftpError::Unreachable(message),
+            _ => other(message),
+        }
+    }
+}
+
+impl From<std::io::Error> for SftpError {
+    fn from(e: std::io::Error) -> Self {
+        SftpError::FileReadError(e.to_string())
+    }
+}
+
+impl From<HostKeyError> for SftpError {
+    fn from(e: HostKeyError) -> Self {
+        match e {
+            HostKeyError::Mismatch { .. } => SftpError::HostKeyMismatch(e.to_string()),
+            _ => SftpError::HostKeyVerificationFailed(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadResult {
+    pub remote_path: String,
+    /// Path of the file below the part of the remote directory without
+    /// placeholders, i.e. what to append to the base URL
+    pub url_path: String,
+    /// Bytes saved by the PNG optimization pass before sending
+    pub bytes_saved: u64,
+    /// Size of the file as uploaded, after any processing
+    pub size: u64,
+    /// Hex SHA-256 of the file as uploaded
+    pub sha256: String,
+}
+
+/// A keyboard-interactive challenge the stored password can't answer
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthChallenge {
+    pub username: String,
+    pub instructions: String,
+    pub prompts: Vec<AuthChallengePrompt>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthChallengePrompt {
+    pub text: String,
+    /// Whether the answer may be shown while typing
+    pub echo: bool,
+}
+
+/// Asks the user to answer keyboard-interactive challenges and to confirm
+/// host keys seen for the first time
+pub trait AuthPrompter: Send + Sync {
+    /// Return one response per prompt, or `None` if the user cancelled
+    fn prompt(&self, challenge: &AuthChallenge) -> Option<Vec<String>>;
+
+    /// Return true if the user trusts the host key
+    fn confirm_host_key(&self, key: &UnknownHostKey) -> bool;
+}
+
+/// Answers keyboard-interactive prompts, using the stored password for a
+/// password prompt and handing anything else to the user
+struct InteractiveResponder<'a> {
+    password: Option<&'a str>,
+    password_sent: bool,
+    prompter: Option<&'a dyn AuthPrompter>,
+}
+
+impl KeyboardInteractivePrompt for InteractiveResponder<'_> {
+    fn prompt<'b>(
+        &mut self,
+        username: &str,
+        instructions: &str,
+        prompts: &[Prompt<'b>],
+    ) -> Vec<String> {
+        if prompts.is_empty() {
+            return Vec::new();
+        }
+
+        // Only send the stored password once, so a rejected password falls
+        // through to the user instead of being retried
+        let is_password_prompt =
+            |p: &Prompt<'b>| !p.echo && p.text.to_lowercase().contains("password");
+        if let Some(password) = self.password {
+            if !self.password_sent && prompts.len() == 1 && is_password_prompt(&prompts[0]) {
+                self.password_sent = true;
+                return vec![password.to_string()];
+            }
+        }
+
+        let challenge = AuthChallenge {
+            username: username.to_string(),
+            instructions: instructions.to_string(),
+            prompts: prompts
+                .iter()
+                .map(|p| AuthChallengePrompt {
+                    text: p.text.to_string(),
+                    echo: p.echo,
+                })
+                .collect(),
+        };
+
+        self.prompter
+            .and_then(|prompter| prompter.prompt(&challenge))
+            .unwrap_or_default()
+    }
+}
+
+/// Permission bits applied to uploaded files and created directories.
+/// `None` leaves whatever the server picks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RemoteModes {
+    pub file_mode: Option<u32>,
+    pub dir_mode: Option<u32>,
+}
+
+impl RemoteModes {
+    /// Problems with the configured modes that would keep a web server
+    /// running as another user from serving the uploads
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if let Some(mode) = self.file_mode {
+            if mode & 0o004 == 0 {
+                warnings.push(format!(
+                    "File mode {:04o} is not readable by other users, so the web server may not be able to serve uploads",
+                    mode
+                ));
+            }
+        }
+
+        if let Some(mode) = self.dir_mode {
+            if mode & 0o001 == 0 {
+                warnings.push(format!(
+                    "Directory mode {:04o} can't be traversed by other users, so the web server may not be able to reach uploads",
+                    mode
+                ));
+            }
+        }
+
+        warnings
+    }
+}
+
+/// Credentials used to authenticate an SFTP session
+#[derive(Debug, Clone)]
+pub enum SftpAuth {
+    /// Identities held by the running ssh-agent
+    Agent,
+    Password(String),
+    /// Prompts answered by the user, with a stored password if there is one
+    KeyboardInteractive,
+    KeyFile {
+        path: PathBuf,
+        passphrase: Option<String>,
+    },
+    KeyData {
+        private_key: String,
+        passphrase: Option<String>,
+    },
+}
+
+impl SftpAuth {
+    /// Build credentials for the configured auth method. `password` is only
+    /// used for password auth; an empty password or passphrase counts as none.
+    /// Password auth without a stored password falls back to
+    /// keyboard-interactive, so the user is asked instead.
+    pub fn from_config(
+        method: &SftpAuthMethod,
+        password: &str,
+        key_passphrase: &str,
+    ) -> Result<Self, SftpError> {
+        let passphrase = Some(key_passphrase.to_string()).filter(|p| !p.is_empty());
+
+        match method {
+            SftpAuthMethod::Agent => Ok(SftpAuth::Agent),
+            SftpAuthMethod::Password if password.is_empty() => Ok(SftpAuth::KeyboardInteractive),
+            SftpAuthMethod::Password => Ok(SftpAuth::Password(password.to_string())),
+            SftpAuthMethod::KeyboardInteractive => Ok(SftpAuth::KeyboardInteractive),
+            SftpAuthMethod::KeyFile { path } if path.trim().is_empty() => Err(
+                SftpError::InvalidConfig("Private key path cannot be empty".to_string()),
+            ),
+            SftpAuthMethod::KeyFile { path } => Ok(SftpAuth::KeyFile {
+                path: expand_home(path.trim()),
+                passphrase,
+            }),
+            SftpAuthMethod::KeyData { private_key } if private_key.trim().is_empty() => Err(
+                SftpError::InvalidConfig("Private key cannot be empty".to_string()),
+            ),
+            SftpAuthMethod::KeyData { private_key } => Ok(SftpAuth::KeyData {
+                private_key: private_key.clone(),
+                passphrase,
+            }),
+        }
+    }
+
+    /// Build credentials for each configured method, keeping their order
+    pub fn from_methods(
+        methods: &[SftpAuthMethod],
+        password: &str,
+        key_passphrase: &str,
+    ) -> Result<Vec<Self>, SftpError> {
+        if methods.is_empty() {
+            return Err(SftpError::InvalidConfig(
+                "No authentication methods configured".to_string(),
+            ));
+        }
+
+        methods
+            .iter()
+            .map(|method| Self::from_config(method, password, key_passphrase))
+            .collect()
+    }
+
+    /// Name of the SSH authentication method these credentials use
+    fn server_method(&self) -> &'static str {
+        match self {
+            SftpAuth::Password(_) => "password",
+            SftpAuth::KeyboardInteractive => "keyboard-interactive",
+            SftpAuth::Agent | SftpAuth::KeyFile { .. } | SftpAuth::KeyData { .. } => "publickey",
+        }
+    }
+
+    fn authenticate(
+        &self,
+        session: &Session,
+        username: &str,
+        prompter: Option<&dyn AuthPrompter>,
+    ) -> Result<(), SftpError> {
+        match self {
+            SftpAuth::Agent => authenticate_with_agent(session, username),
+            SftpAuth::KeyboardInteractive => {
+                authenticate_interactive(session, username, None, prompter)
+            }
+            SftpAuth::Password(password) => {
+                session.userauth_password(username, password).map_err(|e| {
+                    SftpError::AuthenticationFailed(format!(
+                        "Password authentication failed for user '{}': {}",
+                        username, e
+                    ))
+                })
+            }
+            SftpAuth::KeyFile { path, passphrase } => {
+                if !path.is_file() {
+                    return Err(SftpError::InvalidConfig(format!(
+                        "Private key file not found: {}",
+                        path.display()
+                    )));
+                }
+
+                session
+                    .userauth_pubkey_file(username, None, path, passphrase.as_deref())
+                    .map_err(|e| {
+                        SftpError::AuthenticationFailed(format!(
+                            "Key authentication with '{}' failed for user '{}': {}",
+                            path.display(),
+                            username,
+                            e
+                        ))
+                    })
+            }
+            SftpAuth::KeyData {
+                private_key,
+                passphrase,
+            } => session
+                .userauth_pubkey_memory(username, None, private_key, passphrase.as_deref())
+                .map_err(|e| {
+                    SftpError::AuthenticationFailed(format!(
+                        "Key authentication failed for user '{}': {}",
+                        username, e
+                    ))
+                }),
+        }
+    }
+}
+
+/// Run keyboard-interactive auth, answering a password prompt with
+/// `password` and handing every other prompt to `prompter`
+fn authenticate_interactive(
+    session: &Session,
+    username: &str,
+    password: Option<&str>,
+    prompter: Option<&dyn AuthPrompter>,
+) -> Result<(), SftpError> {
+    let mut responder = InteractiveResponder {
+        password,
+        password_sent: false,
+        prompter,
+    };
+
+    session
+        .userauth_keyboard_interactive(username, &mut responder)
+        .map_err(|e| {
+            SftpError::AuthenticationFailed(format!(
+                "Keyboard-interactive authentication failed for user '{}': {}",
+                username, e
+            ))
+        })
+}
+
+/// Offer each identity loaded in the ssh-agent until the server accepts one
+fn authenticate_with_agent(session: &Session, username: &str) -> Result<(), SftpError> {
+    #[cfg(unix)]
+    if std::env::var_os("SSH_AUTH_SOCK").is_none() {
+        return Err(SftpError::AuthenticationFailed(
+            "ssh-agent is not available (SSH_AUTH_SOCK is not set)".to_string(),
+        ));
+    }
+
+    let mut agent = session
+        .agent()
+        .map_err(|e| SftpError::AuthenticationFailed(format!("ssh-agent unavailable: {}", e)))?;
+    agent.connect().map_err(|e| {
+        SftpError::AuthenticationFailed(format!("Failed to connect to ssh-agent: {}", e))
+    })?;
+    agent.list_identities().map_err(|e| {
+        SftpError::AuthenticationFailed(format!("Failed to list ssh-agent identities: {}", e))
+    })?;
+
+    let identities = agent.identities().map_err(|e| {
+        SftpError::AuthenticationFailed(format!("Failed to list ssh-agent identities: {}", e))
+    })?;
+    if identities.is_empty() {
+        let _ = agent.disconnect();
+        return Err(SftpError::AuthenticationFailed(
+            "ssh-agent has no identities loaded".to_string(),
+        ));
+    }
+
+    let accepted = identities
+        .iter()
+        .any(|identity| agent.userauth(username, identity).is_ok());
+    let _ = agent.disconnect();
+
+    if accepted {
+        Ok(())
+    } else {
+        Err(SftpError::AuthenticationFailed(format!(
+            "None of the {} ssh-agent identities were accepted for user '{}'",
+            identities.len(),
+            username
+        )))
+    }
+}
+
+/// Resolve a leading `~/` against the user's home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
 pub struct SftpUploader {
     host: String,
     port: u16,
     username: String,
-    password: Option<String>,
+    auth: Vec<SftpAuth>,
     remote_path: String,
+    profile: String,
+    modes: RemoteModes,
+    output: OutputConfig,
+    upload_limits: UploadLimits,
+    naming: NamingStrategy,
+    collision_policy: CollisionPolicy,
+    retry: RetryConfig,
+    prompter: Option<Arc<dyn AuthPrompter>>,
+    sessions: Option<(SessionManager, Duration)>,
+    progress: Option<ProgressSink>,
+    cancel: Option<Arc<AtomicBool>>,
 }
 
 impl SftpUploader {
@@ -32,7 +520,7 @@ impl SftpUploader {
         host: String,
         port: u16,
         username: String,
-        password: Option<String>,
+        auth: Vec<SftpAuth>,
         remote_path: String,
     ) -> Result<Self, SftpError> {
         if host.is_empty() {
@@ -43,158 +531,1030 @@ impl SftpUploader {
                 "Username cannot be empty".to_string(),
             ));
         }
+        if auth.is_empty() {
+            return Err(SftpError::InvalidConfig(
+                "No authentication methods configured".to_string(),
+            ));
+        }
 
         Ok(Self {
             host,
             port,
             username,
-            password,
+            auth,
             remote_path,
+            profile: String::from("default"),
+            modes: RemoteModes::default(),
+            output: OutputConfig::default(),
+            upload_limits: UploadLimits::default(),
+            naming: NamingStrategy::default(),
+            collision_policy: CollisionPolicy::default(),
+            retry: RetryConfig::default(),
+            prompter: None,
+            sessions: None,
+            progress: None,
+            cancel: None,
         })
     }
 
-    /// Test connection to the SFTP server
-    pub fn test_connection(&self) -> Result<(), SftpError> {
+    /// Profile name substituted for `{profile}` in the remote path
+    pub fn with_profile(mut self, profile: &str) -> Self {
+        self.profile = profile.to_string();
+        self
+    }
+
+    /// Permissions to set on uploaded files and on directories created for them
+    pub fn with_modes(mut self, modes: RemoteModes) -> Self {
+        self.modes = modes;
+        self
+    }
+
+    /// Encoder quality and PNG optimization used when preparing files
+    pub fn with_output_config(mut self, output: OutputConfig) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Downscale and recompress files that exceed these limits before sending
+    pub fn with_upload_limits(mut self, limits: UploadLimits) -> Self {
+        self.upload_limits = limits;
+        self
+    }
+
+    /// Choose how remote files are named and what happens when the name is
+    /// already taken
+    pub fn with_naming(
+        mut self,
+        naming: NamingStrategy,
+        collision_policy: CollisionPolicy,
+    ) -> Self {
+        self.naming = naming;
+        self.collision_policy = collision_policy;
+        self
+    }
+
+    /// Retry dropped connections and timeouts with exponential backoff
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Ask the user to answer keyboard-interactive prompts the stored
+    /// password can't
+    pub fn with_auth_prompter(mut self, prompter: Arc<dyn AuthPrompter>) -> Self {
+        self.prompter = Some(prompter);
+        self
+    }
+
+    /// Reuse sessions kept by `manager`, and hand sessions back to it to be
+    /// kept open for up to `idle_timeout` after each upload
+    pub fn with_session_manager(mut self, manager: SessionManager, idle_timeout: Duration) -> Self {
+        self.sessions = Some((manager, idle_timeout));
+        self
+    }
+
+    /// Report upload progress for `upload_id` to `callback`
+    pub fn with_progress(
+        mut self,
+        upload_id: String,
+        callback: impl Fn(&UploadProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(ProgressSink {
+            upload_id,
+            callback: Box::new(callback),
+        });
+        self
+    }
+
+    /// Stop the upload with `SftpError::Cancelled` once `flag` is set
+    pub fn with_cancel_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(flag);
+        self
+    }
+
+    fn check_cancelled(&self) -> Result<(), SftpError> {
+        match &self.cancel {
+            Some(flag) if flag.load(Ordering::SeqCst) => Err(SftpError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    fn session_key(&self) -> SessionKey {
+        SessionKey {
+            host: self.host.clone(),
+            port: self.port,
+            username: self.username.clone(),
+        }
+    }
+
+    /// Run `op` until it succeeds, retrying transient network errors with
+    /// exponential backoff. Anything else is returned straight away.
+    fn retrying<T>(&self, op: impl Fn() -> Result<T, SftpError>) -> Result<T, SftpError> {
+        let mut retries = 0;
+
+        loop {
+            match op() {
+                Err(e) if e.is_transient() && retries < self.retry.max_retries => {
+                    let delay = retry_delay(&self.retry, retries);
+                    retries += 1;
+                    eprintln!(
+                        "{} - retrying in {} ms ({}/{})",
+                        e,
+                        delay.as_millis(),
+                        retries,
+                        self.retry.max_retries
+                    );
+                    self.sleep_unless_cancelled(delay)?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn sleep_unless_cancelled(&self, duration: Duration) -> Result<(), SftpError> {
+        let deadline = Instant::now() + duration;
+
+        loop {
+            self.check_cancelled()?;
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(());
+            }
+            std::thread::sleep(remaining.min(RETRY_SLEEP_STEP));
+        }
+    }
+
+    /// Run `op` on an SFTP channel, reusing a pooled session when there is
+    /// one. If the pooled session turns out to be dead, `op` is retried once
+    /// on a fresh connection. Any other error means the session still works,
+    /// so it goes back to the pool and the error is returned as is.
+    fn with_sftp<T>(
+        &self,
+        timeout_ms: u32,
+        op: impl Fn(&Sftp) -> Result<T, SftpError>,
+    ) -> Result<T, SftpError> {
+        if let Some((manager, idle_timeout)) = &self.sessions {
+            if let Some(pooled) = manager.take(&self.session_key()) {
+                pooled.session.set_timeout(timeout_ms);
+                match op(&pooled.sftp) {
+                    Ok(value) => {
+                        manager.put(self.session_key(), pooled, *idle_timeout);
+                        return Ok(value);
+                    }
+                    Err(e) if e.is_connection_error() => {
+                        eprintln!("Reused SFTP session failed, reconnecting: {}", e)
+                    }
+                    // The session is fine, the operation itself failed or the
+                    // user stopped the upload
+                    Err(e) => {
+                        manager.put(self.session_key(), pooled, *idle_timeout);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        self.check_cancelled()?;
+        let session = self.connect(timeout_ms);
+        // Connecting can block on a host key or keyboard-interactive prompt,
+        // and a prompt dropped because of a cancel shows up as an auth failure
+        self.check_cancelled()?;
+        let session = session?;
+        let sftp = session.sftp().map_err(|e| {
+            let message = format!("Failed to start SFTP session: {}", e);
+            SftpError::from_ssh(&e, message, SftpError::UploadFailed)
+        })?;
+        self.check_cancelled()?;
+        let value = op(&sftp)?;
+
+        if let Some((manager, idle_timeout)) = &self.sessions {
+            manager.put(
+                self.session_key(),
+                PooledSession { session, sftp },
+                *idle_timeout,
+            );
+        }
+
+        Ok(value)
+    }
+
+    /// Open an authenticated SSH session with the given timeout in milliseconds
+    fn connect(&self, timeout_ms: u32) -> Result<Session, SftpError> {
         let addr = format!("{}:{}", self.host, self.port);
         let tcp = TcpStream::connect_timeout(
             &addr
                 .to_socket_addrs()
-                .map_err(|e| {
-                    SftpError::ConnectionFailed(format!("Invalid host/port '{}': {}", addr, e))
-                })?
+                .map_err(|e| SftpError::DnsFailed(format!("'{}': {}", addr, e)))?
                 .next()
                 .ok_or_else(|| {
-                    SftpError::ConnectionFailed(format!("Could not resolve host: {}", self.host))
+                    SftpError::DnsFailed(format!("No address found for {}", self.host))
                 })?,
             Duration::from_secs(10),
         )
         .map_err(|e| {
-            SftpError::ConnectionFailed(format!(
+            let message = format!(
                 "Cannot reach {}:{}. Check host and port. Error: {}",
                 self.host, self.port, e
-            ))
+            );
+            SftpError::from_io(&e, message, SftpError::ConnectionFailed)
         })?;
 
         let mut session = Session::new().map_err(|e| SftpError::ConnectionFailed(e.to_string()))?;
         session.set_tcp_stream(tcp);
-        session.set_timeout(10000); // 10 second timeout
-        session
-            .handshake()
-            .map_err(|e| SftpError::ConnectionFailed(format!("SSH handshake failed: {}", e)))?;
-
-        // Authenticate with password
-        if let Some(ref password) = self.password {
-            session
-                .userauth_password(&self.username, password)
-                .map_err(|e| {
-                    SftpError::AuthenticationFailed(format!(
-                        "Password authentication failed for user '{}': {}",
-                        self.username, e
-                    ))
-                })?;
-        } else {
-            return Err(SftpError::AuthenticationFailed(
-                "Password is required for authentication".to_string(),
-            ));
+        session.set_timeout(timeout_ms);
+        session.set_keepalive(false, KEEPALIVE_INTERVAL.as_secs() as u32);
+        session.handshake().map_err(|e| {
+            let message = format!("SSH handshake failed: {}", e);
+            SftpError::from_ssh(&e, message, SftpError::ConnectionFailed)
+        })?;
+
+        // Verify the server before sending it any credentials. Unknown keys
+        // are rejected when there's nobody to ask.
+        known_hosts::verify(&session, &self.host, self.port, |key| {
+            self.prompter
+                .as_ref()
+                .is_some_and(|prompter| prompter.confirm_host_key(key))
+        })?;
+
+        let accepted: Vec<String> = match session.auth_methods(&self.username) {
+            Ok(methods) => methods
+                .split(',')
+                .map(|m| m.trim().to_string())
+                .filter(|m| !m.is_empty())
+                .collect(),
+            // The server let us in without asking for credentials
+            Err(_) if session.authenticated() => return Ok(session),
+            Err(e) => {
+                return Err(SftpError::AuthenticationFailed(format!(
+                    "Failed to query authentication methods: {}",
+                    e
+                )))
+            }
+        };
+        let offers = |method: &str| accepted.iter().any(|m| m == method);
+
+        let password = self.auth.iter().find_map(|auth| match auth {
+            SftpAuth::Password(password) => Some(password.as_str()),
+            _ => None,
+        });
+
+        // Try each configured method the server offers, in order, until one is
+        // accepted. Password auth goes through keyboard-interactive when the
+        // server only offers that.
+        let mut failures = Vec::new();
+        let mut tried_interactive = false;
+        for auth in &self.auth {
+            let result = if offers(auth.server_method()) {
+                tried_interactive |= matches!(auth, SftpAuth::KeyboardInteractive);
+                auth.authenticate(&session, &self.username, self.prompter.as_deref())
+            } else if matches!(auth, SftpAuth::Password(_)) && offers("keyboard-interactive") {
+                tried_interactive = true;
+                self.authenticate_interactive(&session, password)
+            } else {
+                failures.push(format!(
+                    "{} authentication is not offered by the server",
+                    auth.server_method()
+                ));
+                continue;
+            };
+
+            match result {
+                Ok(()) if session.authenticated() => return Ok(session),
+                Ok(()) => failures.push(format!(
+                    "{} authentication was not accepted",
+                    auth.server_method()
+                )),
+                Err(e) => failures.push(e.to_string()),
+            }
         }
 
-        if !session.authenticated() {
-            return Err(SftpError::AuthenticationFailed(
-                "Authentication failed - check username and password".to_string(),
-            ));
+        // Nothing configured worked, but the server can ask the user directly
+        if !tried_interactive && offers("keyboard-interactive") && self.prompter.is_some() {
+            match self.authenticate_interactive(&session, password) {
+                Ok(()) if session.authenticated() => return Ok(session),
+                Ok(()) => failures
+                    .push("keyboard-interactive authentication was not accepted".to_string()),
+                Err(e) => failures.push(e.to_string()),
+            }
         }
 
+        Err(SftpError::AuthMethodsRejected { accepted, failures })
+    }
+
+    fn authenticate_interactive(
+        &self,
+        session: &Session,
+        password: Option<&str>,
+    ) -> Result<(), SftpError> {
+        authenticate_interactive(session, &self.username, password, self.prompter.as_deref())
+    }
+
+    /// Test connection to the SFTP server
+    pub fn test_connection(&self) -> Result<(), SftpError> {
+        self.retrying(|| self.connect(10000))?; // 10 second timeout
         Ok(())
     }
 
+    /// Decide what to send for `local_path`. Files that need resizing or
+    /// optimization are processed in memory; anything else is streamed
+    /// straight from disk.
+    fn prepare_source(&self, local_path: &Path) -> Result<(UploadSource, u64), SftpError> {
+        let file_len = std::fs::metadata(local_path)
+            .map_err(|e| SftpError::FileReadError(e.to_string()))?
+            .len();
+
+        let needs_processing = self.upload_limits.max_dimension.is_some()
+            || self
+                .upload_limits
+                .max_file_size
+                .is_some_and(|max| file_len > max)
+            || self.output.png_optimization != PngOptimization::Off;
+
+        if !needs_processing {
+            return Ok((UploadSource::File(local_path.to_path_buf(), file_len), 0));
+        }
+
+        let mut contents = Vec::new();
+        File::open(local_path)
+            .and_then(|mut file| file.read_to_end(&mut contents))
+            .map_err(|e| SftpError::FileReadError(e.to_string()))?;
+
+        self.check_cancelled()?;
+
+        let contents = encoding::fit_for_upload(contents, &self.upload_limits, &self.output)
+            .map_err(|e| SftpError::ImageProcessingFailed(e.to_string()))?;
+        self.check_cancelled()?;
+
+        let optimized = encoding::optimize_png(contents, self.output.png_optimization);
+        self.check_cancelled()?;
+
+        Ok((UploadSource::Memory(optimized.data), optimized.bytes_saved))
+    }
+
     pub fn upload_file(
         &self,
         local_file_path: &str,
         remote_filename: &str,
-    ) -> Result<String, SftpError> {
-        // Connect to the SSH server with timeout
-        let addr = format!("{}:{}", self.host, self.port);
-        let tcp = TcpStream::connect_timeout(
-            &addr
-                .to_socket_addrs()
-                .map_err(|e| {
-                    SftpError::ConnectionFailed(format!("Invalid host/port '{}': {}", addr, e))
-                })?
-                .next()
-                .ok_or_else(|| {
-                    SftpError::ConnectionFailed(format!("Could not resolve host: {}", self.host))
-                })?,
-            Duration::from_secs(10),
-        )
-        .map_err(|e| {
-            SftpError::ConnectionFailed(format!(
-                "Cannot reach {}:{}. Check host and port. Error: {}",
-                self.host, self.port, e
-            ))
+    ) -> Result<UploadResult, SftpError> {
+        // Prepare the local file before connecting, so slow resizing or
+        // optimization can't run into the session timeout. The local file
+        // itself is never modified.
+        let (source, bytes_saved) = self.prepare_source(Path::new(local_file_path))?;
+
+        // Expand placeholders such as {yyyy}/{mm}/{dd} in the configured directory
+        let remote_dir = remote_path::expand(&self.remote_path, &PathContext::new(&self.profile))
+            .map_err(|e| SftpError::InvalidConfig(e.to_string()))?;
+        // Keep a lone "/" so uploads to the root don't become relative paths
+        let remote_dir = match remote_dir.trim_end_matches('/') {
+            "" if remote_dir.starts_with('/') => "/",
+            trimmed => trimmed,
+        };
+        let sha256 = remote_name::sha256_hex(&mut *source.open()?)?;
+        self.check_cancelled()?;
+        let namer = RemoteNamer::new(self.naming, remote_filename, &sha256);
+
+        // 30 second timeout for upload
+        let remote_file_path = self.retrying(|| {
+            self.with_sftp(30000, |sftp| {
+                create_remote_dir_all(sftp, remote_dir, self.modes.dir_mode)?;
+
+                let mut first_attempt = 0;
+                loop {
+                    let (attempt, remote_file_path) =
+                        self.free_remote_path(sftp, remote_dir, &namer, first_attempt)?;
+
+                    match self.write_atomically(sftp, &source, &remote_file_path) {
+                        // The name was taken between checking and renaming
+                        Err(SftpError::RemoteFileExists(taken))
+                            if self.collision_policy == CollisionPolicy::Rename =>
+                        {
+                            println!("'{}' was taken meanwhile, picking another name", taken);
+                            first_attempt = attempt + 1;
+                        }
+                        written => return written.map(|()| remote_file_path),
+                    }
+                }
+            })
         })?;
 
-        let mut session = Session::new().map_err(|e| SftpError::ConnectionFailed(e.to_string()))?;
-        session.set_tcp_stream(tcp);
-        session.set_timeout(30000); // 30 second timeout for upload
-        session
-            .handshake()
-            .map_err(|e| SftpError::ConnectionFailed(format!("SSH handshake failed: {}", e)))?;
-
-        // Authenticate with password
-        if let Some(ref password) = self.password {
-            session
-                .userauth_password(&self.username, password)
+        let url_path = remote_path::url_path(&self.remote_path, &remote_file_path);
+
+        Ok(UploadResult {
+            remote_path: remote_file_path,
+            url_path,
+            bytes_saved,
+            size: source.len(),
+            sha256,
+        })
+    }
+
+    /// Delete a file from the server. A file that is already gone counts as
+    /// deleted.
+    pub fn delete_remote_file(&self, remote_file_path: &str) -> Result<(), SftpError> {
+        self.retrying(|| self.with_sftp(10000, |sftp| unlink_remote(sftp, remote_file_path)))
+    }
+
+    /// List a remote directory, directories first and then by name. An empty
+    /// path lists the login directory.
+    pub fn list_remote_dir(&self, remote_dir: &str) -> Result<Vec<RemoteEntry>, SftpError> {
+        let dir = if remote_dir.is_empty() {
+            "."
+        } else {
+            remote_dir
+        };
+
+        let mut entries = self
+            .retrying(|| {
+                self.with_sftp(10000, |sftp| {
+                    sftp.readdir(Path::new(dir)).map_err(|e| {
+                        let message = format!("Failed to list '{}': {}", dir, e);
+                        SftpError::from_ssh(&e, message, SftpError::RemoteOperationFailed)
+                    })
+                })
+            })?
+            .into_iter()
+            .filter_map(|(path, stat)| {
+                let name = path.file_name()?.to_string_lossy().to_string();
+                let path = format!("{}/{}", dir.trim_end_matches('/'), name);
+                Some(RemoteEntry::new(name, path, &stat))
+            })
+            .collect::<Vec<_>>();
+
+        entries.sort_by(|a, b| {
+            (b.kind == RemoteEntryKind::Directory)
+                .cmp(&(a.kind == RemoteEntryKind::Directory))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Ok(entries)
+    }
+
+    /// Look up a single remote file or directory
+    pub fn stat_remote(&self, remote_path: &str) -> Result<RemoteEntry, SftpError> {
+        let stat = self.retrying(|| {
+            self.with_sftp(10000, |sftp| {
+                sftp.lstat(Path::new(remote_path)).map_err(|e| {
+                    let message = format!("Failed to stat '{}': {}", remote_path, e);
+                    SftpError::from_ssh(&e, message, SftpError::RemoteOperationFailed)
+                })
+            })
+        })?;
+
+        let name = remote_path
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or(remote_path)
+            .to_string();
+        Ok(RemoteEntry::new(name, remote_path.to_string(), &stat))
+    }
+
+    /// Rename or move a remote file or directory. Refuses to replace
+    /// anything already at `to`.
+    pub fn rename_remote(&self, from: &str, to: &str) -> Result<(), SftpError> {
+        self.retrying(|| {
+            self.with_sftp(10000, |sftp| {
+                if sftp.lstat(Path::new(to)).is_ok() {
+                    return Err(SftpError::RemoteFileExists(to.to_string()));
+                }
+
+                sftp.rename(
+                    Path::new(from),
+                    Path::new(to),
+                    Some(RenameFlags::ATOMIC | RenameFlags::NATIVE),
+                )
                 .map_err(|e| {
-                    SftpError::AuthenticationFailed(format!(
-                        "Password authentication failed for user '{}': {}",
-                        self.username, e
+                    let message = format!("Failed to rename '{}' to '{}': {}", from, to, e);
+                    SftpError::from_ssh(&e, message, SftpError::RemoteOperationFailed)
+                })
+            })
+        })
+    }
+
+    /// Delete a remote file, or a directory if it is empty. A file that is
+    /// already gone counts as deleted.
+    pub fn delete_remote(&self, remote_path: &str) -> Result<(), SftpError> {
+        self.retrying(|| {
+            self.with_sftp(10000, |sftp| {
+                let path = Path::new(remote_path);
+                if !sftp.lstat(path).is_ok_and(|stat| stat.is_dir()) {
+                    return unlink_remote(sftp, remote_path);
+                }
+
+                sftp.rmdir(path).map_err(|e| {
+                    let message = format!("Failed to delete '{}': {}", remote_path, e);
+                    SftpError::from_ssh(&e, message, SftpError::RemoteOperationFailed)
+                })
+            })
+        })
+    }
+
+    /// Find a name in `remote_dir` that isn't taken yet, following the
+    /// collision policy. Candidates before `first_attempt` are skipped, and
+    /// the attempt the name came from is returned with it.
+    fn free_remote_path(
+        &self,
+        sftp: &Sftp,
+        remote_dir: &str,
+        namer: &RemoteNamer,
+        first_attempt: u32,
+    ) -> Result<(u32, String), SftpError> {
+        for attempt in first_attempt..MAX_NAME_ATTEMPTS {
+            let filename = namer.candidate(attempt);
+            let remote_file_path = if remote_dir.is_empty() {
+                filename
+            } else if remote_dir.ends_with('/') {
+                format!("{}{}", remote_dir, filename)
+            } else {
+                format!("{}/{}", remote_dir, filename)
+            };
+
+            match sftp.stat(Path::new(&remote_file_path)) {
+                Err(e) if e.code() == ErrorCode::SFTP(LIBSSH2_FX_NO_SUCH_FILE) => {
+                    return Ok((attempt, remote_file_path))
+                }
+                Err(e) => {
+                    return Err(SftpError::from_ssh(
+                        &e,
+                        e.to_string(),
+                        SftpError::UploadFailed,
                     ))
-                })?;
+                }
+                Ok(_) => {}
+            }
+
+            match self.collision_policy {
+                CollisionPolicy::Refuse => {
+                    return Err(SftpError::RemoteFileExists(remote_file_path))
+                }
+                CollisionPolicy::Rename => {
+                    println!(
+                        "'{}' already exists, picking another name",
+                        remote_file_path
+                    );
+                }
+            }
+        }
+
+        Err(SftpError::UploadFailed(format!(
+            "No free file name found after {} attempts",
+            MAX_NAME_ATTEMPTS
+        )))
+    }
+
+    /// Write `source` to a hidden `.name.part` file next to `remote_file_path`
+    /// and rename it into place once its size checks out, so a dropped
+    /// connection never leaves a truncated file at the public URL
+    fn write_atomically(
+        &self,
+        sftp: &Sftp,
+        source: &UploadSource,
+        remote_file_path: &str,
+    ) -> Result<(), SftpError> {
+        let remote = Path::new(remote_file_path);
+        let part_path = part_path(remote_file_path);
+
+        let written = sftp
+            .create(&part_path)
+            .map_err(|e| SftpError::from_ssh(&e, e.to_string(), SftpError::UploadFailed))
+            .and_then(|mut part_file| {
+                self.transfer(&mut *source.open()?, &mut part_file, source.len())
+            })
+            .and_then(|()| {
+                let size = sftp
+                    .stat(&part_path)
+                    .map_err(|e| SftpError::from_ssh(&e, e.to_string(), SftpError::UploadFailed))?
+                    .size
+                    .unwrap_or_default();
+
+                if size == source.len() {
+                    Ok(())
+                } else {
+                    Err(SftpError::UploadFailed(format!(
+                        "Remote file has {} bytes, expected {}",
+                        size,
+                        source.len()
+                    )))
+                }
+            })
+            // Set the mode before renaming, so a rejected chmod is cleaned up
+            // like any other failure instead of leaving the file published
+            .and_then(|()| match self.modes.file_mode {
+                Some(mode) => set_mode(sftp, &part_path, mode),
+                None => Ok(()),
+            })
+            .and_then(|()| rename_into_place(sftp, &part_path, remote));
+
+        if written.is_err() {
+            // Best effort, the connection may already be gone
+            if let Err(e) = sftp.unlink(&part_path) {
+                eprintln!(
+                    "Failed to remove partial upload '{}': {}",
+                    part_path.display(),
+                    e
+                );
+            }
+        }
+
+        written
+    }
+
+    /// Copy `reader` to `writer` in chunks, reporting progress as it goes
+    fn transfer(
+        &self,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+        total_bytes: u64,
+    ) -> Result<(), SftpError> {
+        let mut progress = self
+            .progress
+            .as_ref()
+            .map(|sink| ProgressTracker::new(sink, total_bytes));
+        let mut buffer = vec![0u8; UPLOAD_CHUNK_SIZE];
+
+        loop {
+            self.check_cancelled()?;
+
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(SftpError::FileReadError(e.to_string())),
+            };
+
+            writer
+                .write_all(&buffer[..read])
+                .map_err(|e| SftpError::from_io(&e, e.to_string(), SftpError::UploadFailed))?;
+
+            if let Some(progress) = progress.as_mut() {
+                progress.advance(read as u64);
+            }
+        }
+
+        writer
+            .flush()
+            .map_err(|e| SftpError::from_io(&e, e.to_string(), SftpError::UploadFailed))?;
+
+        if let Some(progress) = progress.as_mut() {
+            progress.finish();
+        }
+
+        Ok(())
+    }
+}
+
+/// Delay before retry number `retry` (counting from 0): the initial delay
+/// grown by the multiplier for each earlier retry, capped at the maximum,
+/// with up to the jitter fraction of it taken off at random so clients that
+/// failed together don't all retry at the same moment
+fn retry_delay(config: &RetryConfig, retry: u32) -> Duration {
+    let base = config.initial_delay_ms as f64 * config.multiplier.max(1.0).powi(retry as i32);
+    let capped = base.min(config.max_delay_ms as f64);
+    let jitter = config.jitter.clamp(0.0, 1.0) * rand::thread_rng().gen::<f64>();
+
+    Duration::from_millis((capped * (1.0 - jitter)) as u64)
+}
+
+/// Delete a remote file. A file that is already gone counts as deleted.
+fn unlink_remote(sftp: &Sftp, remote_file_path: &str) -> Result<(), SftpError> {
+    match sftp.unlink(Path::new(remote_file_path)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.code() == ErrorCode::SFTP(LIBSSH2_FX_NO_SUCH_FILE) => Ok(()),
+        Err(e) => {
+            let message = format!("Failed to delete '{}': {}", remote_file_path, e);
+            Err(SftpError::from_ssh(
+                &e,
+                message,
+                SftpError::RemoteOperationFailed,
+            ))
+        }
+    }
+}
+
+/// Set the permission bits of a remote file or directory
+fn set_mode(sftp: &Sftp, path: &Path, mode: u32) -> Result<(), SftpError> {
+    let stat = FileStat {
+        size: None,
+        uid: None,
+        gid: None,
+        perm: Some(mode),
+        atime: None,
+        mtime: None,
+    };
+
+    sftp.setstat(path, stat).map_err(|e| {
+        SftpError::UploadFailed(format!(
+            "Failed to set mode {:04o} on '{}': {}",
+            mode,
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Create `dir` and any missing parents on the server, like `mkdir -p`.
+/// Only directories created here get `dir_mode`; existing ones are left alone.
+fn create_remote_dir_all(sftp: &Sftp, dir: &str, dir_mode: Option<u32>) -> Result<(), SftpError> {
+    if dir.is_empty() || sftp.stat(Path::new(dir)).is_ok() {
+        return Ok(());
+    }
+
+    let mut current = String::new();
+    if dir.starts_with('/') {
+        current.push('/');
+    }
+
+    for segment in dir.split('/').filter(|segment| !segment.is_empty()) {
+        if !current.is_empty() && !current.ends_with('/') {
+            current.push('/');
+        }
+        current.push_str(segment);
+
+        let path = Path::new(&current);
+        if sftp.stat(path).is_ok() {
+            continue;
+        }
+
+        let mode = dir_mode.unwrap_or(DEFAULT_DIR_MODE);
+        match sftp.mkdir(path, mode as i32) {
+            // mkdir is subject to the server's umask, so set the mode explicitly
+            Ok(()) if dir_mode.is_some() => set_mode(sftp, path, mode)?,
+            Ok(()) => {}
+            // Another upload may have created it in the meantime
+            Err(_) if sftp.stat(path).is_ok() => {}
+            Err(e) => {
+                let message = format!("Failed to create remote directory '{}': {}", current, e);
+                return Err(SftpError::from_ssh(&e, message, SftpError::UploadFailed));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Hidden temporary name an upload is written to before being renamed.
+/// Built with string operations so the remote path keeps its `/` separators
+/// on Windows too.
+fn part_path(remote_file_path: &str) -> PathBuf {
+    let part = match remote_file_path.rsplit_once('/') {
+        Some((dir, name)) => format!("{}/.{}.part", dir, name),
+        None => format!(".{}.part", remote_file_path),
+    };
+    PathBuf::from(part)
+}
+
+/// Rename `from` to `to` without replacing anything already there. If
+/// another client took the name since it was checked, the upload fails with
+/// `RemoteFileExists` instead of overwriting that file.
+fn rename_into_place(sftp: &Sftp, from: &Path, to: &Path) -> Result<(), SftpError> {
+    let flags = RenameFlags::ATOMIC | RenameFlags::NATIVE;
+    let Err(rename_error) = sftp.rename(from, to, Some(flags)) else {
+        return Ok(());
+    };
+
+    if sftp.lstat(to).is_ok() {
+        return Err(SftpError::RemoteFileExists(to.display().to_string()));
+    }
+
+    Err(SftpError::from_ssh(
+        &rename_error,
+        format!("Failed to move upload into place: {}", rename_error),
+        SftpError::UploadFailed,
+    ))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteEntryKind {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+/// A file or directory on the server, as shown by the remote browser
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub path: String,
+    pub kind: RemoteEntryKind,
+    pub size: Option<u64>,
+    /// Last modification as a Unix timestamp in seconds
+    pub modified: Option<u64>,
+}
+
+impl RemoteEntry {
+    fn new(name: String, path: String, stat: &FileStat) -> Self {
+        let file_type = stat.file_type();
+        let kind = if file_type.is_dir() {
+            RemoteEntryKind::Directory
+        } else if file_type.is_symlink() {
+            RemoteEntryKind::Symlink
+        } else if file_type.is_file() {
+            RemoteEntryKind::File
         } else {
-            return Err(SftpError::AuthenticationFailed(
-                "Password is required for authentication".to_string(),
-            ));
+            RemoteEntryKind::Other
+        };
+
+        Self {
+            name,
+            path,
+            kind,
+            size: stat.size,
+            modified: stat.mtime,
         }
+    }
+}
 
-        if !session.authenticated() {
-            return Err(SftpError::AuthenticationFailed(
-                "Authentication failed - check username and password".to_string(),
-            ));
+/// What gets sent to the server: the local file as-is, or a processed copy
+enum UploadSource {
+    File(PathBuf, u64),
+    Memory(Vec<u8>),
+}
+
+impl UploadSource {
+    fn len(&self) -> u64 {
+        match self {
+            UploadSource::File(_, len) => *len,
+            UploadSource::Memory(data) => data.len() as u64,
         }
+    }
 
-        // Open SFTP session
-        let sftp = session
-            .sftp()
-            .map_err(|e| SftpError::UploadFailed(format!("Failed to start SFTP session: {}", e)))?;
+    /// Open a fresh reader, so a retried upload starts from the beginning
+    fn open(&self) -> Result<Box<dyn Read + '_>, SftpError> {
+        match self {
+            UploadSource::File(path, _) => File::open(path)
+                .map(|file| Box::new(BufReader::new(file)) as Box<dyn Read>)
+                .map_err(|e| SftpError::FileReadError(e.to_string())),
+            UploadSource::Memory(data) => Ok(Box::new(&data[..])),
+        }
+    }
+}
 
-        // Read the local file
-        let local_path = Path::new(local_file_path);
-        let mut file =
-            File::open(local_path).map_err(|e| SftpError::FileReadError(e.to_string()))?;
+/// Progress of a running upload, sent as the `upload-progress` event
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadProgress {
+    pub upload_id: String,
+    pub bytes_sent: u64,
+    pub total_bytes: u64,
+    /// Average throughput since the transfer started
+    pub bytes_per_second: f64,
+}
 
-        let mut contents = Vec::new();
-        file.read_to_end(&mut contents)
-            .map_err(|e| SftpError::FileReadError(e.to_string()))?;
+struct ProgressSink {
+    upload_id: String,
+    callback: Box<dyn Fn(&UploadProgress) + Send + Sync>,
+}
+
+/// Counts bytes sent and reports them at most every `PROGRESS_INTERVAL`
+struct ProgressTracker<'a> {
+    sink: &'a ProgressSink,
+    total_bytes: u64,
+    bytes_sent: u64,
+    started: Instant,
+    last_report: Option<Instant>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    fn new(sink: &'a ProgressSink, total_bytes: u64) -> Self {
+        let mut tracker = Self {
+            sink,
+            total_bytes,
+            bytes_sent: 0,
+            started: Instant::now(),
+            last_report: None,
+        };
+        tracker.report();
+        tracker
+    }
 
-        // Construct the remote file path
-        let remote_file_path = if self.remote_path.is_empty() {
-            remote_filename.to_string()
+    fn advance(&mut self, bytes: u64) {
+        self.bytes_sent += bytes;
+
+        if self
+            .last_report
+            .is_none_or(|last| last.elapsed() >= PROGRESS_INTERVAL)
+        {
+            self.report();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.report();
+    }
+
+    fn report(&mut self) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let bytes_per_second = if elapsed > 0.0 {
+            self.bytes_sent as f64 / elapsed
         } else {
-            format!(
-                "{}/{}",
-                self.remote_path.trim_end_matches('/'),
-                remote_filename
-            )
+            0.0
         };
 
-        // Upload the file
-        let mut remote_file = sftp
-            .create(Path::new(&remote_file_path))
-            .map_err(|e| SftpError::UploadFailed(e.to_string()))?;
+        (self.sink.callback)(&UploadProgress {
+            upload_id: self.sink.upload_id.clone(),
+            bytes_sent: self.bytes_sent,
+            total_bytes: self.total_bytes,
+            bytes_per_second,
+        });
+        self.last_report = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part_path_hides_partial_upload_next_to_target() {
+        assert_eq!(
+            part_path("/srv/shots/a.png"),
+            PathBuf::from("/srv/shots/.a.png.part")
+        );
+        assert_eq!(part_path("/a.png"), PathBuf::from("/.a.png.part"));
+        assert_eq!(part_path("a.png"), PathBuf::from(".a.png.part"));
+    }
+
+    fn retry_config(jitter: f64) -> RetryConfig {
+        RetryConfig {
+            max_retries: 5,
+            initial_delay_ms: 500,
+            max_delay_ms: 3_000,
+            multiplier: 2.0,
+            jitter,
+        }
+    }
+
+    #[test]
+    fn retry_delay_grows_by_multiplier() {
+        let config = retry_config(0.0);
+        assert_eq!(retry_delay(&config, 0), Duration::from_millis(500));
+        assert_eq!(retry_delay(&config, 1), Duration::from_millis(1_000));
+        assert_eq!(retry_delay(&config, 2), Duration::from_millis(2_000));
+    }
+
+    #[test]
+    fn retry_delay_is_capped() {
+        let config = retry_config(0.0);
+        assert_eq!(retry_delay(&config, 3), Duration::from_millis(3_000));
+        assert_eq!(retry_delay(&config, 30), Duration::from_millis(3_000));
+    }
+
+    #[test]
+    fn retry_delay_jitter_only_shortens_delay() {
+        let config = retry_config(0.5);
+        for _ in 0..100 {
+            let delay = retry_delay(&config, 1);
+            assert!(delay >= Duration::from_millis(500), "{:?}", delay);
+            assert!(delay <= Duration::from_millis(1_000), "{:?}", delay);
+        }
+
+        // Out of range jitter is clamped to the whole delay at most
+        let config = retry_config(3.0);
+        for _ in 0..100 {
+            assert!(retry_delay(&config, 1) <= Duration::from_millis(1_000));
+        }
+    }
+
+    #[test]
+    fn config_and_auth_errors_are_not_transient() {
+        assert!(!SftpError::AuthenticationFailed("denied".to_string()).is_transient());
+        assert!(!SftpError::InvalidConfig("no host".to_string()).is_transient());
+    }
+
+    #[test]
+    fn unreachable_and_timed_out_sockets_are_transient() {
+        use std::io::{Error, ErrorKind};
+
+        for kind in [ErrorKind::ConnectionRefused, ErrorKind::TimedOut] {
+            let error = SftpError::from_io(
+                &Error::from(kind),
+                "connect".to_string(),
+                SftpError::ConnectionFailed,
+            );
+            assert!(error.is_transient(), "{:?}", kind);
+        }
+
+        let error = SftpError::from_io(
+            &Error::from(ErrorKind::PermissionDenied),
+            "connect".to_string(),
+            SftpError::ConnectionFailed,
+        );
+        assert!(!error.is_transient());
+    }
+
+    #[test]
+    fn classifies_libssh2_errors() {
+        let timeout = ssh2::Error::new(ErrorCode::Session(LIBSSH2_ERROR_TIMEOUT), "timed out");
+        let error = SftpError::from_ssh(&timeout, "upload".to_string(), SftpError::UploadFailed);
+        assert!(matches!(error, SftpError::Timeout(_)));
 
-        std::io::copy(&mut &contents[..], &mut remote_file)
-            .map_err(|e| SftpError::UploadFailed(e.to_string()))?;
+        let lost = ssh2::Error::new(ErrorCode::SFTP(LIBSSH2_FX_CONNECTION_LOST), "lost");
+        let error = SftpError::from_ssh(&lost, "upload".to_string(), SftpError::UploadFailed);
+        assert!(matches!(error, SftpError::ConnectionReset(_)));
 
-        Ok(remote_file_path)
+        let missing = ssh2::Error::new(ErrorCode::SFTP(LIBSSH2_FX_NO_SUCH_FILE), "missing");
+        let error = SftpError::from_ssh(&missing, "upload".to_string(), SftpError::UploadFailed);
+        assert!(matches!(error, SftpError::UploadFailed(_)));
+        assert!(!error.is_transient());
     }
 }
@@ -0,0 +1,257 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::fs;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Largest page `list` returns, whatever the caller asks for
+const MAX_PAGE_SIZE: u32 = 200;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadHistoryError {
+    #[error("Failed to open upload history: {0}")]
+    OpenFailed(String),
+    #[error("Upload history query failed: {0}")]
+    QueryFailed(String),
+    #[error("No upload with id {0} in the history")]
+    NotFound(i64),
+    #[error("Upload history is unavailable: {0}")]
+    Unavailable(String),
+}
+
+impl From<rusqlite::Error> for UploadHistoryError {
+    fn from(e: rusqlite::Error) -> Self {
+        UploadHistoryError::QueryFailed(e.to_string())
+    }
+}
+
+/// A finished upload as stored in the history
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadRecord {
+    pub id: i64,
+    pub local_path: String,
+    pub remote_path: String,
+    pub url: String,
+    pub profile: String,
+    /// Server the file was uploaded to
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    /// Size in bytes as uploaded
+    pub size: u64,
+    /// Hex SHA-256 of the uploaded file
+    pub sha256: String,
+    /// Unix timestamp in seconds
+    pub uploaded_at: i64,
+}
+
+impl UploadRecord {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            local_path: row.get("local_path")?,
+            remote_path: row.get("remote_path")?,
+            url: row.get("url")?,
+            profile: row.get("profile")?,
+            host: row.get("host")?,
+            port: row.get("port")?,
+            username: row.get("username")?,
+            size: row.get::<_, i64>("size")? as u64,
+            sha256: row.get("sha256")?,
+            uploaded_at: row.get("uploaded_at")?,
+        })
+    }
+}
+
+/// What gets recorded for a new upload
+pub struct NewUploadRecord<'a> {
+    pub local_path: &'a str,
+    pub remote_path: &'a str,
+    pub url: &'a str,
+    pub profile: &'a str,
+    pub host: &'a str,
+    pub port: u16,
+    pub username: &'a str,
+    pub size: u64,
+    pub sha256: &'a str,
+}
+
+/// One page of history, newest first
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadHistoryPage {
+    pub records: Vec<UploadRecord>,
+    /// Number of records matching the search across all pages
+    pub total: u64,
+}
+
+/// Every successful upload, kept in `history.db` in the config directory so
+/// links can be found again and files retracted later.
+///
+/// If the database can't be opened the app still runs without a history,
+/// and every operation fails with `Unavailable`.
+pub struct UploadHistory {
+    connection: Result<Mutex<Connection>, String>,
+}
+
+impl UploadHistory {
+    /// Open the history database, creating it on first use
+    pub fn open() -> Result<Self, UploadHistoryError> {
+        let config_dir = dirs::config_dir().ok_or_else(|| {
+            UploadHistoryError::OpenFailed("Failed to get config directory".to_string())
+        })?;
+        let app_config_dir = config_dir.join("shot-share");
+        fs::create_dir_all(&app_config_dir).map_err(|e| {
+            UploadHistoryError::OpenFailed(format!("Failed to create config directory: {}", e))
+        })?;
+
+        let path = app_config_dir.join("history.db");
+        let connection = Connection::open(&path)
+            .map_err(|e| UploadHistoryError::OpenFailed(format!("'{}': {}", path.display(), e)))?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS uploads (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                local_path TEXT NOT NULL,
+                remote_path TEXT NOT NULL,
+                url TEXT NOT NULL,
+                profile TEXT NOT NULL,
+                host TEXT NOT NULL,
+                port INTEGER NOT NULL,
+                username TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                sha256 TEXT NOT NULL,
+                uploaded_at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS uploads_uploaded_at ON uploads (uploaded_at);",
+        )?;
+
+        Ok(Self {
+            connection: Ok(Mutex::new(connection)),
+        })
+    }
+
+    /// A history that couldn't be opened because of `error`
+    pub fn unavailable(error: &UploadHistoryError) -> Self {
+        Self {
+            connection: Err(error.to_string()),
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Connection>, UploadHistoryError> {
+        match &self.connection {
+            Ok(connection) => Ok(connection.lock().unwrap_or_else(PoisonError::into_inner)),
+            Err(error) => Err(UploadHistoryError::Unavailable(error.clone())),
+        }
+    }
+
+    /// Add a finished upload, returning its record
+    pub fn record(&self, upload: NewUploadRecord<'_>) -> Result<UploadRecord, UploadHistoryError> {
+        let uploaded_at = chrono::Utc::now().timestamp();
+        let connection = self.lock()?;
+        connection.execute(
+            "INSERT INTO uploads
+                (local_path, remote_path, url, profile, host, port, username, size, sha256, uploaded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                upload.local_path,
+                upload.remote_path,
+                upload.url,
+                upload.profile,
+                upload.host,
+                upload.port,
+                upload.username,
+                upload.size as i64,
+                upload.sha256,
+                uploaded_at
+            ],
+        )?;
+
+        Ok(UploadRecord {
+            id: connection.last_insert_rowid(),
+            local_path: upload.local_path.to_string(),
+            remote_path: upload.remote_path.to_string(),
+            url: upload.url.to_string(),
+            profile: upload.profile.to_string(),
+            host: upload.host.to_string(),
+            port: upload.port,
+            username: upload.username.to_string(),
+            size: upload.size,
+            sha256: upload.sha256.to_string(),
+            uploaded_at,
+        })
+    }
+
+    /// Page through the history, newest first. `search` matches anywhere in
+    /// the local path, remote path, URL or profile.
+    pub fn list(
+        &self,
+        search: Option<&str>,
+        page: u32,
+        page_size: u32,
+    ) -> Result<UploadHistoryPage, UploadHistoryError> {
+        let page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        let offset = i64::from(page) * i64::from(page_size);
+        let pattern = match search.map(str::trim).filter(|search| !search.is_empty()) {
+            Some(search) => format!("%{}%", escape_like(search)),
+            None => "%".to_string(),
+        };
+
+        let filter = "local_path LIKE ?1 ESCAPE '\\' OR remote_path LIKE ?1 ESCAPE '\\'
+             OR url LIKE ?1 ESCAPE '\\' OR profile LIKE ?1 ESCAPE '\\'";
+        let connection = self.lock()?;
+
+        let total: i64 = connection.query_row(
+            &format!("SELECT COUNT(*) FROM uploads WHERE {}", filter),
+            params![pattern],
+            |row| row.get(0),
+        )?;
+
+        let mut statement = connection.prepare(&format!(
+            "SELECT * FROM uploads WHERE {} ORDER BY uploaded_at DESC, id DESC LIMIT ?2 OFFSET ?3",
+            filter
+        ))?;
+        let records = statement
+            .query_map(
+                params![pattern, i64::from(page_size), offset],
+                UploadRecord::from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(UploadHistoryPage {
+            records,
+            total: total as u64,
+        })
+    }
+
+    pub fn get(&self, id: i64) -> Result<UploadRecord, UploadHistoryError> {
+        self.lock()?
+            .query_row(
+                "SELECT * FROM uploads WHERE id = ?1",
+                params![id],
+                UploadRecord::from_row,
+            )
+            .optional()?
+            .ok_or(UploadHistoryError::NotFound(id))
+    }
+
+    /// Forget an upload. The remote file is not touched.
+    pub fn remove(&self, id: i64) -> Result<(), UploadHistoryError> {
+        match self
+            .lock()?
+            .execute("DELETE FROM uploads WHERE id = ?1", params![id])?
+        {
+            0 => Err(UploadHistoryError::NotFound(id)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Escape the LIKE wildcards in user input so they match literally
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,304 @@
+use crate::services::settings::SftpConfig;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadQueueError {
+    #[error("Failed to access upload queue: {0}")]
+    Io(String),
+    #[error("Failed to parse upload queue: {0}")]
+    Parse(String),
+    #[error("No queued upload with id '{0}'")]
+    NotFound(String),
+    #[error("An upload with id '{0}' is already queued")]
+    AlreadyQueued(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuedUploadStatus {
+    /// Waiting for the worker, retried whenever the server is reachable
+    Pending,
+    /// Currently being sent by the worker
+    Uploading,
+    /// Failed in a way retrying won't fix, e.g. rejected credentials. Only
+    /// tried again when the user asks for it.
+    Failed,
+}
+
+/// Server and directory a queued upload is meant for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadTarget {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub remote_path: String,
+}
+
+impl From<&SftpConfig> for UploadTarget {
+    fn from(sftp: &SftpConfig) -> Self {
+        Self {
+            host: sftp.host.clone(),
+            port: sftp.port,
+            username: sftp.username.clone(),
+            remote_path: sftp.remote_path.clone(),
+        }
+    }
+}
+
+/// An upload that failed or was deferred, kept until it succeeds or is dropped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedUpload {
+    pub id: String,
+    pub file_path: String,
+    pub filename: String,
+    /// Where the upload was headed when it was queued. It's only sent while
+    /// the saved connection still points there.
+    pub target: UploadTarget,
+    /// Unix timestamp in seconds
+    pub queued_at: i64,
+    pub status: QueuedUploadStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+/// Uploads waiting to be sent, persisted to `upload_queue.json` in the
+/// config directory so they survive a restart.
+///
+/// The background worker sleeps in `wait` and is woken early by `wake`
+/// whenever there's something new to try.
+pub struct UploadQueue {
+    path: PathBuf,
+    items: Mutex<Vec<QueuedUpload>>,
+    woken: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl UploadQueue {
+    /// Load the queue saved by a previous run, or start with an empty one.
+    ///
+    /// A queue file that can't be read doesn't keep the app from starting:
+    /// it's moved aside to `upload_queue.json.bad` and the queue starts empty.
+    pub fn load() -> Self {
+        let config_dir = dirs::config_dir().unwrap_or_else(|| {
+            eprintln!(
+                "Failed to get config directory, keeping the upload queue in the temp directory"
+            );
+            std::env::temp_dir()
+        });
+        let path = config_dir.join("shot-share").join("upload_queue.json");
+
+        let mut items = Self::read(&path).unwrap_or_else(|e| {
+            eprintln!("{}, starting with an empty upload queue", e);
+            let bad_path = path.with_extension("json.bad");
+            if let Err(e) = fs::rename(&path, &bad_path) {
+                eprintln!(
+                    "Failed to move '{}' to '{}': {}",
+                    path.display(),
+                    bad_path.display(),
+                    e
+                );
+            }
+            Vec::new()
+        });
+
+        // The app quit part way through these
+        for item in items.iter_mut() {
+            if item.status == QueuedUploadStatus::Uploading {
+                item.status = QueuedUploadStatus::Pending;
+            }
+        }
+
+        Self {
+            path,
+            items: Mutex::new(items),
+            woken: Mutex::new(false),
+            wake: Condvar::new(),
+        }
+    }
+
+    /// Read the queue file at `path`, which counts as empty if it's missing
+    fn read(path: &Path) -> Result<Vec<QueuedUpload>, UploadQueueError> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents = fs::read_to_string(path).map_err(|e| {
+            UploadQueueError::Io(format!("Failed to read '{}': {}", path.display(), e))
+        })?;
+        serde_json::from_str(&contents)
+            .map_err(|e| UploadQueueError::Parse(format!("'{}': {}", path.display(), e)))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<QueuedUpload>> {
+        self.items.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Write the queue to disk through a temporary file, so a crash while
+    /// saving never leaves a truncated queue behind
+    fn save(&self, items: &[QueuedUpload]) -> Result<(), UploadQueueError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                UploadQueueError::Io(format!("Failed to create config directory: {}", e))
+            })?;
+        }
+
+        let json = serde_json::to_string_pretty(items)
+            .map_err(|e| UploadQueueError::Parse(e.to_string()))?;
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, json).map_err(|e| {
+            UploadQueueError::Io(format!("Failed to write '{}': {}", temp_path.display(), e))
+        })?;
+        fs::rename(&temp_path, &self.path).map_err(|e| {
+            UploadQueueError::Io(format!("Failed to save '{}': {}", self.path.display(), e))
+        })
+    }
+
+    /// Apply `change` to the item with `id` and save the queue
+    fn update(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut QueuedUpload),
+    ) -> Result<QueuedUpload, UploadQueueError> {
+        let mut items = self.lock();
+        let item = items
+            .iter_mut()
+            .find(|item| item.id == id)
+            .ok_or_else(|| UploadQueueError::NotFound(id.to_string()))?;
+        change(item);
+
+        let updated = item.clone();
+        self.save(&items)?;
+        Ok(updated)
+    }
+
+    /// All queued uploads in the order they'll be tried
+    pub fn list(&self) -> Vec<QueuedUpload> {
+        self.lock().clone()
+    }
+
+    /// Add an upload to the end of the queue. Fails if an upload with the
+    /// same id is already queued, rather than replacing it.
+    pub fn push(
+        &self,
+        id: &str,
+        file_path: &str,
+        filename: &str,
+        target: UploadTarget,
+        last_error: Option<String>,
+    ) -> Result<QueuedUpload, UploadQueueError> {
+        let item = QueuedUpload {
+            id: id.to_string(),
+            file_path: file_path.to_string(),
+            filename: filename.to_string(),
+            target,
+            queued_at: chrono::Utc::now().timestamp(),
+            status: QueuedUploadStatus::Pending,
+            attempts: u32::from(last_error.is_some()),
+            last_error,
+        };
+
+        let mut items = self.lock();
+        if items.iter().any(|queued| queued.id == id) {
+            return Err(UploadQueueError::AlreadyQueued(id.to_string()));
+        }
+        items.push(item.clone());
+        self.save(&items)?;
+        drop(items);
+
+        // An upload that just failed would most likely fail again right away,
+        // so it waits for the next interval
+        if item.last_error.is_none() {
+            self.wake();
+        }
+        Ok(item)
+    }
+
+    /// Remove an upload from the queue, returning it
+    pub fn remove(&self, id: &str) -> Result<QueuedUpload, UploadQueueError> {
+        let mut items = self.lock();
+        let index = items
+            .iter()
+            .position(|item| item.id == id)
+            .ok_or_else(|| UploadQueueError::NotFound(id.to_string()))?;
+
+        let removed = items.remove(index);
+        self.save(&items)?;
+        Ok(removed)
+    }
+
+    /// Move an upload to `position`, clamped to the end of the queue
+    pub fn move_to(&self, id: &str, position: usize) -> Result<(), UploadQueueError> {
+        let mut items = self.lock();
+        let index = items
+            .iter()
+            .position(|item| item.id == id)
+            .ok_or_else(|| UploadQueueError::NotFound(id.to_string()))?;
+
+        let item = items.remove(index);
+        let position = position.min(items.len());
+        items.insert(position, item);
+        self.save(&items)
+    }
+
+    /// Mark an upload as pending again and wake the worker to try it now
+    pub fn retry(&self, id: &str) -> Result<QueuedUpload, UploadQueueError> {
+        let item = self.update(id, |item| {
+            if item.status == QueuedUploadStatus::Failed {
+                item.status = QueuedUploadStatus::Pending;
+            }
+        })?;
+
+        self.wake();
+        Ok(item)
+    }
+
+    /// Take the first pending upload and mark it as uploading
+    pub fn start_next(&self) -> Result<Option<QueuedUpload>, UploadQueueError> {
+        let Some(id) = self
+            .lock()
+            .iter()
+            .find(|item| item.status == QueuedUploadStatus::Pending)
+            .map(|item| item.id.clone())
+        else {
+            return Ok(None);
+        };
+
+        self.update(&id, |item| item.status = QueuedUploadStatus::Uploading)
+            .map(Some)
+    }
+
+    /// Record a failed attempt. Pending uploads are tried again
+    /// automatically, failed ones only when the user retries them.
+    pub fn record_failure(
+        &self,
+        id: &str,
+        error: String,
+        status: QueuedUploadStatus,
+    ) -> Result<QueuedUpload, UploadQueueError> {
+        self.update(id, |item| {
+            item.status = status;
+            item.attempts += 1;
+            item.last_error = Some(error);
+        })
+    }
+
+    /// Block until `wake` is called or `timeout` passes
+    pub fn wait(&self, timeout: Duration) {
+        let woken = self.woken.lock().unwrap_or_else(PoisonError::into_inner);
+        let (mut woken, _) = self
+            .wake
+            .wait_timeout_while(woken, timeout, |woken| !*woken)
+            .unwrap_or_else(PoisonError::into_inner);
+        *woken = false;
+    }
+
+    /// Make the worker look at the queue now instead of at its next interval
+    pub fn wake(&self) {
+        *self.woken.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.wake.notify_all();
+    }
+}
@@ -1,6 +1,7 @@
+use crate::commands::show_region_selector;
 use crate::services::Settings;
 use std::error::Error;
-use tauri::{AppHandle, Emitter};
+use tauri::AppHandle;
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
 
 pub fn setup_global_shortcuts(app: &AppHandle) -> Result<(), Box<dyn Error>> {
@@ -14,7 +15,7 @@ pub fn setup_global_shortcuts(app: &AppHandle) -> Result<(), Box<dyn Error>> {
             .global_shortcut()
             .on_shortcut(shortcut, move |app, _shortcut, event| {
                 if event.state == ShortcutState::Pressed {
-                    let _ = app.emit("show-region-selector", ());
+                    show_region_selector(app);
                 }
             }) {
             Ok(_) => {
@@ -1,3 +1,4 @@
+use crate::commands::show_region_selector;
 use std::error::Error;
 use std::io::Cursor;
 use tauri::image::Image;
@@ -58,7 +59,7 @@ pub fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn Error>> {
         .icon(icon)
         .on_menu_event(move |app, event| match event.id().as_ref() {
             "take-screenshot" => {
-                let _ = app.emit("show-region-selector", ());
+                show_region_selector(app);
             }
             "show" => {
                 if let Some(window) = app.get_webview_window("main") {
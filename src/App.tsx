@@ -8,13 +8,19 @@ import { Sidebar, type NavigationItem } from "./components/Sidebar";
 import { HomePage } from "./components/HomePage";
 import { GeneralSettings } from "./components/GeneralSettings";
 import { SftpSettings } from "./components/SftpSettings";
-import { Toaster } from "sonner";
+import { AuthPromptDialog } from "./components/AuthPromptDialog";
+import { HostKeyDialog } from "./components/HostKeyDialog";
+import { Toaster, toast } from "sonner";
 import type { Settings } from "./types/settings";
+import type { RegionSelectorPayload } from "./types/capture";
+import type { QueuedUploadFinished } from "./types/upload";
+import { bytesToObjectUrl, revokeImageUrl } from "./services/imageUtils";
 import "./index.css"
 
 function App() {
   const [screenshotDataUrl, setScreenshotDataUrl] = useState<string | null>(null);
   const [croppedImageDataUrl, setCroppedImageDataUrl] = useState<string | null>(null);
+  const [frozenFrameId, setFrozenFrameId] = useState<number | null>(null);
   const [currentView, setCurrentView] = useState<NavigationItem>("home");
 
   useEffect(() => {
@@ -41,8 +47,16 @@ function App() {
     });
 
     // Listen for global shortcut to show region selector
-    const unlistenShortcut = listen("show-region-selector", () => {
-      startScreenshot();
+    const unlistenShortcut = listen<RegionSelectorPayload>("show-region-selector", (event) => {
+      startScreenshot(event.payload);
+    });
+
+    // Uploads from the offline queue finish in the background
+    const unlistenQueuedUpload = listen<QueuedUploadFinished>("queued-upload-finished", (event) => {
+      const { filename, url, copied_to_clipboard } = event.payload;
+      toast.success(`Queued upload of ${filename} finished`, {
+        description: copied_to_clipboard ? "Link copied to clipboard" : url,
+      });
     });
 
     // Listen for window close events to clean up state
@@ -57,11 +71,12 @@ function App() {
       unlistenUploadSettings.then(fn => fn());
       unlistenAbout.then(fn => fn());
       unlistenShortcut.then(fn => fn());
+      unlistenQueuedUpload.then(fn => fn());
       unlistenClose.then(fn => fn());
     };
   }, []);
 
-  async function startScreenshot() {
+  async function startScreenshot(payload?: RegionSelectorPayload) {
     try {
       const window = getCurrentWindow();
 
@@ -71,12 +86,20 @@ function App() {
         await window.setFullscreen(false);
       }
 
-      await window.hide();
-      await new Promise(resolve => setTimeout(resolve, 100));
+      let bytes: ArrayBuffer;
+      const frameId = payload?.active_frame_id ?? null;
 
-      const dataUrl = await invoke<string>("capture_full_screenshot");
+      if (frameId !== null) {
+        // The screen was already frozen by the backend when the shortcut fired
+        bytes = await invoke<ArrayBuffer>("get_frozen_frame", { frameId });
+      } else {
+        await window.hide();
+        await new Promise(resolve => setTimeout(resolve, 100));
+        bytes = await invoke<ArrayBuffer>("capture_full_screenshot");
+      }
 
-      setScreenshotDataUrl(dataUrl);
+      setFrozenFrameId(frameId);
+      setScreenshotDataUrl(bytesToObjectUrl(bytes));
       await window.setFullscreen(true);
       await window.show();
       await window.setFocus();
@@ -93,8 +116,17 @@ function App() {
     try {
       if (!screenshotDataUrl) return;
 
-      const croppedDataUrl = await cropImage(screenshotDataUrl, x, y, width, height);
-
+      const croppedDataUrl = frozenFrameId !== null
+        ? bytesToObjectUrl(await invoke<ArrayBuffer>("crop_frozen_frame", {
+          frameId: frozenFrameId,
+          region: { x, y, width, height },
+          coordinateSpace: "logical",
+        }))
+        : await cropImage(screenshotDataUrl, x, y, width, height);
+
+      await invoke("discard_frozen_frames");
+      setFrozenFrameId(null);
+      revokeImageUrl(screenshotDataUrl);
       setScreenshotDataUrl(null);
       const window = getCurrentWindow();
       
@@ -149,7 +181,7 @@ function App() {
       // Load settings to get filename prefix
       const settings = await invoke<Settings>("get_settings");
       
-      const base64Data = editedImageDataUrl.replace(/^data:image\/png;base64,/, '');
+      const base64Data = editedImageDataUrl.replace(/^data:image\/[\w.+-]+;base64,/, '');
       
       // Generate filename with optional prefix
       const timestamp = Date.now();
@@ -165,6 +197,7 @@ function App() {
       const window = getCurrentWindow();
       await window.setFullscreen(false);
       await window.hide();
+      revokeImageUrl(croppedImageDataUrl);
       setCroppedImageDataUrl(null);
 
     } catch (error) {
@@ -175,6 +208,7 @@ function App() {
   async function handleEditorCancel() {
     const window = getCurrentWindow();
     await window.hide();
+    revokeImageUrl(croppedImageDataUrl);
     setCroppedImageDataUrl(null);
   }
 
@@ -182,6 +216,9 @@ function App() {
     const window = getCurrentWindow();
     await window.setFullscreen(false);
     await window.hide();
+    await invoke("discard_frozen_frames");
+    setFrozenFrameId(null);
+    revokeImageUrl(screenshotDataUrl);
     setScreenshotDataUrl(null);
   }
 
@@ -225,6 +262,8 @@ function App() {
         </div>
       )}
 
+      <HostKeyDialog />
+      <AuthPromptDialog />
       <Toaster position="top-right" richColors closeButton />
     </>
   );
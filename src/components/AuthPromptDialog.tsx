@@ -0,0 +1,92 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { Button } from "./ui/button";
+import { Input } from "./ui/input";
+import { Label } from "./ui/label";
+import {
+    Dialog,
+    DialogContent,
+    DialogDescription,
+    DialogFooter,
+    DialogHeader,
+    DialogTitle,
+} from "./ui/dialog";
+import type { AuthPromptRequest } from "../types/upload";
+
+/** Answers keyboard-interactive prompts sent by the SFTP server */
+export function AuthPromptDialog() {
+    const [request, setRequest] = useState<AuthPromptRequest | null>(null);
+    const [responses, setResponses] = useState<string[]>([]);
+
+    useEffect(() => {
+        const unlisten = listen<AuthPromptRequest>("sftp-auth-prompt", (event) => {
+            setRequest(event.payload);
+            setResponses(event.payload.prompts.map(() => ""));
+        });
+
+        return () => {
+            unlisten.then(fn => fn());
+        };
+    }, []);
+
+    async function answer(answers: string[] | null) {
+        if (!request) return;
+
+        const requestId = request.request_id;
+        setRequest(null);
+        try {
+            await invoke("answer_sftp_auth_prompt", { requestId, responses: answers });
+        } catch (error) {
+            console.error("Failed to answer authentication prompt:", error);
+        }
+    }
+
+    return (
+        <Dialog open={request !== null} onOpenChange={(open) => !open && answer(null)}>
+            <DialogContent>
+                <form
+                    className="space-y-4"
+                    onSubmit={(e) => {
+                        e.preventDefault();
+                        answer(responses);
+                    }}
+                >
+                    <DialogHeader>
+                        <DialogTitle>SFTP Authentication</DialogTitle>
+                        <DialogDescription>
+                            {request?.instructions || `The server is asking ${request?.username} for more information`}
+                        </DialogDescription>
+                    </DialogHeader>
+
+                    {request?.prompts.map((prompt, index) => (
+                        <div key={index} className="space-y-2">
+                            <Label htmlFor={`authPrompt${index}`} className="text-sm font-medium text-foreground">
+                                {prompt.text}
+                            </Label>
+                            <Input
+                                id={`authPrompt${index}`}
+                                type={prompt.echo ? "text" : "password"}
+                                autoFocus={index === 0}
+                                value={responses[index] ?? ""}
+                                onChange={(e) =>
+                                    setResponses(current =>
+                                        current.map((value, i) => (i === index ? e.target.value : value))
+                                    )
+                                }
+                                className="font-mono text-sm bg-background/50 border-border/50 focus:border-primary transition-all"
+                            />
+                        </div>
+                    ))}
+
+                    <DialogFooter>
+                        <Button type="button" variant="outline" onClick={() => answer(null)}>
+                            Cancel
+                        </Button>
+                        <Button type="submit">Continue</Button>
+                    </DialogFooter>
+                </form>
+            </DialogContent>
+        </Dialog>
+    );
+}
@@ -0,0 +1,72 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { Button } from "./ui/button";
+import {
+    Dialog,
+    DialogContent,
+    DialogDescription,
+    DialogFooter,
+    DialogHeader,
+    DialogTitle,
+} from "./ui/dialog";
+import type { HostKeyPromptRequest } from "../types/upload";
+
+/** Asks the user to trust an SFTP server's host key on first connect */
+export function HostKeyDialog() {
+    const [request, setRequest] = useState<HostKeyPromptRequest | null>(null);
+
+    useEffect(() => {
+        const unlisten = listen<HostKeyPromptRequest>("sftp-host-key-prompt", (event) => {
+            setRequest(event.payload);
+        });
+
+        return () => {
+            unlisten.then(fn => fn());
+        };
+    }, []);
+
+    async function answer(trusted: boolean) {
+        if (!request) return;
+
+        const requestId = request.request_id;
+        setRequest(null);
+        try {
+            await invoke("answer_sftp_host_key", { requestId, trusted });
+        } catch (error) {
+            console.error("Failed to answer host key prompt:", error);
+        }
+    }
+
+    const hostLabel = request
+        ? request.port === 22 ? request.host : `${request.host}:${request.port}`
+        : "";
+
+    return (
+        <Dialog open={request !== null} onOpenChange={(open) => !open && answer(false)}>
+            <DialogContent>
+                <DialogHeader>
+                    <DialogTitle>Unknown SFTP Server</DialogTitle>
+                    <DialogDescription>
+                        This is the first connection to {hostLabel}. Check that the fingerprint
+                        matches the one published by your server administrator before trusting it.
+                    </DialogDescription>
+                </DialogHeader>
+
+                <div className="space-y-1 p-3 rounded-lg bg-background/30 border border-border/30">
+                    <p className="text-xs text-muted-foreground">{request?.key_type}</p>
+                    <p className="font-mono text-sm break-all text-foreground">{request?.fingerprint}</p>
+                </div>
+
+                <DialogFooter>
+                    <Button type="button" variant="outline" onClick={() => answer(false)}>
+                        Cancel
+                    </Button>
+                    <Button type="button" onClick={() => answer(true)}>
+                        Trust and Connect
+                    </Button>
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    );
+}
@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { toast } from "sonner";
+import { dataUrlToBytes } from "./imageUtils";
 
 /**
  * Copies an image to the system clipboard
@@ -14,7 +15,8 @@ export async function copyImageToClipboard(dataUrl: string): Promise<void> {
 
         toast.info("Copying to clipboard...");
         
-        await invoke("copy_image_to_clipboard", { dataUrl });
+        const bytes = await dataUrlToBytes(dataUrl);
+        await invoke("copy_image_to_clipboard", bytes);
         
         toast.success("Image copied to clipboard");
     } catch (error) {
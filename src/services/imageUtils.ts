@@ -27,3 +27,33 @@ export function getCanvasDataUrl(stage: Konva.Stage | null): string {
 export function waitForRender(ms: number = 50): Promise<void> {
     return new Promise(resolve => setTimeout(resolve, ms));
 }
+
+/**
+ * Wraps encoded image bytes received from the backend in an object URL
+ * @param bytes - Encoded image data
+ * @param type - MIME type of the image (default: "image/png")
+ * @returns Object URL usable as an image source; release it with revokeImageUrl
+ */
+export function bytesToObjectUrl(bytes: ArrayBuffer, type: string = "image/png"): string {
+    return URL.createObjectURL(new Blob([bytes], { type }));
+}
+
+/**
+ * Releases an object URL created by bytesToObjectUrl (data URLs are ignored)
+ * @param url - The image URL to release
+ */
+export function revokeImageUrl(url: string | null): void {
+    if (url?.startsWith("blob:")) {
+        URL.revokeObjectURL(url);
+    }
+}
+
+/**
+ * Decodes a data URL into raw bytes for sending over binary IPC
+ * @param dataUrl - Base64 data URL of the image
+ * @returns The decoded image bytes
+ */
+export async function dataUrlToBytes(dataUrl: string): Promise<ArrayBuffer> {
+    const response = await fetch(dataUrl);
+    return response.arrayBuffer();
+}
@@ -1,7 +1,20 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { writeText } from "@tauri-apps/plugin-clipboard-manager";
 import { toast } from "sonner";
 import type { Settings } from "../types/settings";
+import type { SavedImage, UploadProgress, UploadResult } from "../types/upload";
+
+/** Error message of `SftpError::Cancelled` */
+const UPLOAD_CANCELLED = "Upload was cancelled";
+/** Ends the error of an upload the backend put in the offline queue */
+const UPLOAD_QUEUED = "Queued to retry once the server is reachable.";
+
+function formatBytes(bytes: number): string {
+    if (bytes < 1024) return `${bytes} B`;
+    if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+    return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
 
 export async function uploadImageToSftp(
     dataUrl: string,
@@ -19,32 +32,64 @@ export async function uploadImageToSftp(
         // Generate filename with optional prefix and timestamp
         const timestamp = new Date().toISOString().replace(/[:.]/g, "-");
         const prefix = settings.filename_prefix ? `${settings.filename_prefix}_` : '';
-        const filename = `${prefix}screenshot_${timestamp}.png`;
+        const requestedFilename = `${prefix}screenshot_${timestamp}.png`;
 
         // Save the image temporarily
-        const base64Data = dataUrl.replace(/^data:image\/png;base64,/, "");
-        const tempPath = `${settings.save_directory}/${filename}`;
+        const base64Data = dataUrl.replace(/^data:image\/[\w.+-]+;base64,/, "");
+        const requestedPath = `${settings.save_directory}/${requestedFilename}`;
 
         toast.info("Saving image...");
-        await invoke("save_base64_image", {
+        // The backend encodes into the configured output format and adjusts the extension
+        const saved = await invoke<SavedImage>("save_base64_image", {
             base64Data,
-            savePath: tempPath,
+            savePath: requestedPath,
         });
+        const tempPath = saved.path;
+        const filename = tempPath.split(/[\\/]/).pop() ?? requestedFilename;
+
+        const uploadId = crypto.randomUUID();
+        const cancelAction = {
+            label: "Cancel",
+            onClick: () => {
+                invoke("cancel_upload", { uploadId }).catch((cancelError) => {
+                    console.error("Failed to cancel upload:", cancelError);
+                });
+            },
+        };
+        const progressToast = toast.loading("Uploading to SFTP...", { action: cancelAction });
+        const unlistenProgress = await listen<UploadProgress>("upload-progress", (event) => {
+            const progress = event.payload;
+            if (progress.upload_id !== uploadId) return;
 
-        toast.info("Uploading to SFTP...");
-        const remotePath = await invoke<string>("upload_to_sftp", {
-            filePath: tempPath,
-            filename,
-            host: settings.sftp.host,
-            port: settings.sftp.port,
-            username: settings.sftp.username,
-            password: settings.sftp.password,
-            remotePath: settings.sftp.remote_path,
+            const percent = Math.floor((progress.bytes_sent / Math.max(progress.total_bytes, 1)) * 100);
+            toast.loading(`Uploading to SFTP... ${percent}%`, {
+                id: progressToast,
+                action: cancelAction,
+                description: `${formatBytes(progress.bytes_sent)} of ${formatBytes(progress.total_bytes)} at ${formatBytes(progress.bytes_per_second)}/s`,
+            });
         });
 
+        let uploadResult: UploadResult;
+        try {
+            uploadResult = await invoke<UploadResult>("upload_to_sftp", {
+                uploadId,
+                filePath: tempPath,
+                filename,
+                host: settings.sftp.host,
+                port: settings.sftp.port,
+                username: settings.sftp.username,
+                password: settings.sftp.password,
+                remotePath: settings.sftp.remote_path,
+            });
+        } finally {
+            unlistenProgress();
+            toast.dismiss(progressToast);
+        }
+        const { remote_path: remotePath, url_path: urlPath } = uploadResult;
+
         // Construct the public URL
         const baseUrl = settings.sftp.base_url.replace(/\/$/, "");
-        const publicUrl = `${baseUrl}/${filename}`;
+        const publicUrl = `${baseUrl}/${urlPath}`;
 
         // Copy to clipboard if enabled
         if (settings.sftp.copy_to_clipboard) {
@@ -63,6 +108,17 @@ export async function uploadImageToSftp(
 
         setTimeout(onComplete, 1500);
     } catch (error) {
+        if (String(error) === UPLOAD_CANCELLED) {
+            toast.info("Upload cancelled");
+            throw error;
+        }
+        if (String(error).endsWith(UPLOAD_QUEUED)) {
+            toast.warning("Server unreachable, upload queued", {
+                description: "It will be sent automatically once the connection is back.",
+            });
+            throw error;
+        }
+
         console.error("Upload error:", error);
         toast.error("Upload failed", {
             description: String(error)
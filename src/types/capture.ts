@@ -0,0 +1,20 @@
+export interface MonitorInfo {
+    id: number;
+    name: string;
+    x: number;
+    y: number;
+    width: number;
+    height: number;
+    scale_factor: number;
+    is_primary: boolean;
+}
+
+export interface FrozenFrame {
+    id: number;
+    monitor: MonitorInfo;
+}
+
+export interface RegionSelectorPayload {
+    frames: FrozenFrame[];
+    active_frame_id: number | null;
+}
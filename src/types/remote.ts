@@ -0,0 +1,11 @@
+export type RemoteEntryKind = "file" | "directory" | "symlink" | "other";
+
+/** A file or directory on the server, see `list_remote_dir` */
+export interface RemoteEntry {
+    name: string;
+    path: string;
+    kind: RemoteEntryKind;
+    size: number | null;
+    /** Unix timestamp in seconds */
+    modified: number | null;
+}
@@ -1,16 +1,69 @@
+export type SftpAuthMethod =
+    | { type: "agent" }
+    | { type: "password" }
+    | { type: "keyboard_interactive" }
+    | { type: "key_file"; path: string }
+    | { type: "key_data"; private_key: string };
+
+export type NamingStrategy =
+    | { type: "timestamp" }
+    | { type: "random"; length: number }
+    | { type: "content_hash" };
+
+export type CollisionPolicy = "rename" | "refuse";
+
+/** Backoff used to retry dropped connections and timeouts */
+export interface RetryConfig {
+    /** Retries after the first attempt, 0 disables retrying */
+    max_retries: number;
+    initial_delay_ms: number;
+    max_delay_ms: number;
+    multiplier: number;
+    /** Fraction of each delay that is randomized, from 0 to 1 */
+    jitter: number;
+}
+
 export interface SftpConfig {
     host: string;
     port: number;
     username: string;
     password: string;
+    /** Tried in order until one succeeds */
+    auth_methods: SftpAuthMethod[];
+    key_passphrase: string;
+    /** May contain {yyyy}, {mm}, {dd}, {user}, {hostname} and {profile} */
     remote_path: string;
+    naming: NamingStrategy;
+    collision_policy: CollisionPolicy;
+    profile_name: string;
+    /** Permission bits for uploaded files, null to keep the server default */
+    file_mode: number | null;
+    /** Permission bits for created directories, null to keep the server default */
+    dir_mode: number | null;
     base_url: string;
     copy_to_clipboard: boolean;
+    /** Seconds an idle SSH session is kept for reuse, 0 to reconnect every upload */
+    session_idle_timeout_secs: number;
+    max_dimension: number | null;
+    max_file_size: number | null;
+    retry: RetryConfig;
+}
+
+export type OutputFormat = "png" | "jpeg" | "webp" | "avif";
+
+export type PngOptimization = "off" | "fast" | "max";
+
+export interface OutputConfig {
+    format: OutputFormat;
+    quality: number;
+    lossless: boolean;
+    png_optimization: PngOptimization;
 }
 
 export interface Settings {
     save_directory: string;
     screenshot_shortcut: string;
     filename_prefix: string;
+    output: OutputConfig;
     sftp: SftpConfig;
 }
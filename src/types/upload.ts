@@ -0,0 +1,101 @@
+export interface SavedImage {
+    path: string;
+    bytes_saved: number;
+}
+
+export interface UploadResult {
+    remote_path: string;
+    /** Path to append to the base URL */
+    url_path: string;
+    bytes_saved: number;
+    /** Size in bytes as uploaded, after any processing */
+    size: number;
+    /** Hex SHA-256 of the file as uploaded */
+    sha256: string;
+}
+
+/** Payload of the `upload-progress` event */
+export interface UploadProgress {
+    upload_id: string;
+    bytes_sent: number;
+    total_bytes: number;
+    bytes_per_second: number;
+}
+
+export interface AuthChallengePrompt {
+    text: string;
+    echo: boolean;
+}
+
+/** Payload of the `sftp-auth-prompt` event */
+export interface AuthPromptRequest {
+    request_id: number;
+    username: string;
+    instructions: string;
+    prompts: AuthChallengePrompt[];
+}
+
+/** Payload of the `sftp-host-key-prompt` event */
+export interface HostKeyPromptRequest {
+    request_id: number;
+    host: string;
+    port: number;
+    key_type: string;
+    fingerprint: string;
+}
+
+export type QueuedUploadStatus = "pending" | "uploading" | "failed";
+
+/** An upload waiting in the offline queue, see `list_upload_queue` */
+/** Server and directory a queued upload is meant for */
+export interface UploadTarget {
+    host: string;
+    port: number;
+    username: string;
+    remote_path: string;
+}
+
+export interface QueuedUpload {
+    id: string;
+    file_path: string;
+    filename: string;
+    /** Only sent while the saved connection still points here */
+    target: UploadTarget;
+    /** Unix timestamp in seconds */
+    queued_at: number;
+    status: QueuedUploadStatus;
+    attempts: number;
+    last_error: string | null;
+}
+
+/** Payload of the `queued-upload-finished` event */
+export interface QueuedUploadFinished {
+    id: string;
+    filename: string;
+    remote_path: string;
+    url: string;
+    copied_to_clipboard: boolean;
+}
+
+/** A past upload, see `list_upload_history` */
+export interface UploadRecord {
+    id: number;
+    local_path: string;
+    remote_path: string;
+    url: string;
+    profile: string;
+    /** Server the file went to */
+    host: string;
+    port: number;
+    username: string;
+    size: number;
+    sha256: string;
+    /** Unix timestamp in seconds */
+    uploaded_at: number;
+}
+
+export interface UploadHistoryPage {
+    records: UploadRecord[];
+    /** Records matching the search across all pages */
+    total: number;
+}